@@ -979,7 +979,7 @@ async fn handle_rpc_request(
     client_version: String,
 ) -> Result<Value, String> {
     match method {
-        "ping" => Ok(json!({ "ok": true })),
+        "ping" => Ok(json!({ "ok": true, "version": env!("CARGO_PKG_VERSION") })),
         "list_workspaces" => {
             let workspaces = state.list_workspaces().await;
             serde_json::to_value(workspaces).map_err(|err| err.to_string())
@@ -1353,6 +1353,18 @@ async fn handle_client(
             continue;
         }
 
+        if method == "unsubscribe_events" {
+            // RPC-only clients (such as the web gateway's connection pool) opt
+            // out so events they never read don't pile up for them.
+            if let Some(task) = events_task.take() {
+                task.abort();
+            }
+            if let Some(response) = build_result_response(id, json!({ "ok": true })) {
+                let _ = out_tx.send(response);
+            }
+            continue;
+        }
+
         let client_version = format!("daemon-{}", env!("CARGO_PKG_VERSION"));
         let result = handle_rpc_request(&state, &method, params, client_version).await;
         let response = match result {
@@ -1,22 +1,76 @@
+use axum::body::{Body, Bytes};
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
-use axum::extract::{Json, Query, State};
-use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::extract::{ConnectInfo, Json, Query, Request, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::response::{Html, IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::Router;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use futures_util::stream::{self, Stream, StreamExt};
+use futures_util::FutureExt;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::Infallible;
 use std::env;
-use std::net::SocketAddr;
-use std::sync::Arc;
+use std::future::Future;
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Semaphore};
 use tower_http::cors::{Any, CorsLayer};
+use zip::write::{SimpleFileOptions, StreamWriter};
+use zip::{CompressionMethod, ZipWriter};
 
 const DEFAULT_WEB_LISTEN_ADDR: &str = "127.0.0.1:8741";
 const DEFAULT_DAEMON_ADDR: &str = "127.0.0.1:4732";
+const DEFAULT_DAEMON_POOL_SIZE: usize = 4;
+const DEFAULT_DAEMON_TIMEOUT: Duration = Duration::from_secs(30);
 const AUTH_HEADER_PREFIX: &str = "Bearer ";
+const WS_PROTOCOL: &str = "codex-monitor.v1";
+const WS_TOKEN_PROTOCOL_PREFIX: &str = "codex-monitor.token.";
+const WS_RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+const WS_RECONNECT_MAX_DELAY: Duration = Duration::from_secs(8);
+const DEFAULT_WS_RECONNECT_ATTEMPTS: u32 = 10;
+const MAX_WS_PENDING_RPCS: usize = 64;
+const SSE_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+const IDEMPOTENT_REPLAY_HEADER: &str = "idempotent-replay";
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 128;
+const IDEMPOTENCY_TTL: Duration = Duration::from_secs(10 * 60);
+const IDEMPOTENCY_MAX_ENTRIES: usize = 1024;
+const DEFAULT_EXPORT_MAX_THREADS: usize = 500;
+const MAX_EXPORT_MAX_THREADS: usize = 5000;
+const EXPORT_PAGE_SIZE: u32 = 50;
+const EXPORT_RESUME_CONCURRENCY: usize = 3;
+const MAX_CONCURRENT_EXPORTS: usize = 2;
+const EXPORT_RETRY_AFTER_SECS: u64 = 10;
+const DEFAULT_FEED_LIMIT: usize = 50;
+const MAX_FEED_LIMIT: usize = 200;
+const FEED_FETCH_CONCURRENCY: usize = 4;
+const MAX_FEED_EMPTY_PAGES: usize = 5;
+const DRAWINGS_FETCH_CONCURRENCY: usize = 6;
+const DRAWINGS_PAGE_SIZE: u32 = 20;
+const MAX_DRAWINGS_DEPTH: usize = 10;
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 32 * 1024 * 1024;
+const DEFAULT_MAX_EXPORT_THREAD_BYTES: usize = 128 * 1024 * 1024;
+const MAX_IMPORT_MESSAGES: usize = 1000;
+const MAX_IMPORT_TEXT_BYTES: usize = 1024 * 1024;
+const AUDIT_LOG_CAPACITY: usize = 500;
+const AUTH_FAILURE_WINDOW: Duration = Duration::from_secs(60);
+const AUTH_FAILURE_LIMIT: usize = 10;
+const STATS_BUCKET_SECS: i64 = 60 * 60;
+const STATS_BUCKET_COUNT: usize = 48;
+const STATS_RECONNECT_DELAY: Duration = Duration::from_secs(5);
 
 const CONSOLE_HTML: &str = include_str!("web_gateway_console/index.html");
 const CONSOLE_APP_JS: &str = include_str!("web_gateway_console/app.js");
@@ -25,18 +79,32 @@ const CONSOLE_STYLES_CSS: &str = include_str!("web_gateway_console/styles.css");
 #[derive(Clone)]
 struct GatewayState {
     config: Arc<GatewayConfig>,
+    idempotency: Arc<IdempotencyCache>,
+    stats: Arc<EventStats>,
+    audit: Arc<AuthAudit>,
+    daemon: Arc<DaemonPool>,
+    request_ids: Arc<AtomicU64>,
+    /// Exports running at once; each holds a permit until its archive ends.
+    exports: Arc<Semaphore>,
 }
 
 struct GatewayConfig {
     listen: SocketAddr,
     daemon_addr: String,
     daemon_token: Option<String>,
+    daemon_pool_size: usize,
+    daemon_timeout: Duration,
+    ws_reconnect_attempts: u32,
     api_token: Option<String>,
+    audit_successes: bool,
+    max_response_bytes: usize,
+    max_export_thread_bytes: usize,
 }
 
 #[derive(Debug)]
 struct GatewayError {
     status: StatusCode,
+    code: Option<&'static str>,
     message: String,
 }
 
@@ -44,13 +112,47 @@ impl GatewayError {
     fn bad_request(message: impl Into<String>) -> Self {
         Self {
             status: StatusCode::BAD_REQUEST,
+            code: None,
             message: message.into(),
         }
     }
 
-    fn unauthorized(message: impl Into<String>) -> Self {
+    fn unauthorized(code: &'static str, message: impl Into<String>) -> Self {
         Self {
             status: StatusCode::UNAUTHORIZED,
+            code: Some(code),
+            message: message.into(),
+        }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            code: None,
+            message: message.into(),
+        }
+    }
+
+    fn conflict(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::CONFLICT,
+            code: Some(code),
+            message: message.into(),
+        }
+    }
+
+    fn too_many_requests(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            code: None,
+            message: message.into(),
+        }
+    }
+
+    fn payload_too_large(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            code: None,
             message: message.into(),
         }
     }
@@ -58,15 +160,381 @@ impl GatewayError {
     fn daemon(message: impl Into<String>) -> Self {
         Self {
             status: StatusCode::BAD_GATEWAY,
+            code: None,
+            message: message.into(),
+        }
+    }
+
+    fn daemon_timeout(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::GATEWAY_TIMEOUT,
+            code: None,
             message: message.into(),
         }
     }
+
+    fn body(&self) -> Value {
+        match self.code {
+            Some(code) => json!({ "error": self.message, "code": code }),
+            None => json!({ "error": self.message }),
+        }
+    }
 }
 
 impl IntoResponse for GatewayError {
     fn into_response(self) -> Response {
-        let body = Json(json!({ "error": self.message }));
-        (self.status, body).into_response()
+        let body = Json(self.body());
+        let mut response = (self.status, body).into_response();
+        if self.status == StatusCode::UNAUTHORIZED {
+            if let Some(code) = self.code {
+                response.extensions_mut().insert(AuthRejection(code));
+            }
+        }
+        response
+    }
+}
+
+/// Marks a response rejected by `authorize_request` so the audit layer can
+/// record why without seeing the supplied token.
+#[derive(Clone, Copy, Debug)]
+struct AuthRejection(&'static str);
+
+#[derive(Clone, Debug)]
+struct CachedResponse {
+    status: StatusCode,
+    body: Value,
+}
+
+impl CachedResponse {
+    fn from_outcome(outcome: &Result<Value, GatewayError>) -> Self {
+        match outcome {
+            Ok(body) => Self {
+                status: StatusCode::OK,
+                body: body.clone(),
+            },
+            Err(error) => Self {
+                status: error.status,
+                body: error.body(),
+            },
+        }
+    }
+
+    fn into_replay_response(self) -> Response {
+        (
+            self.status,
+            [(
+                HeaderName::from_static(IDEMPOTENT_REPLAY_HEADER),
+                HeaderValue::from_static("true"),
+            )],
+            Json(self.body),
+        )
+            .into_response()
+    }
+}
+
+enum IdempotencyEntry {
+    InFlight,
+    Completed {
+        response: CachedResponse,
+        stored_at: Instant,
+    },
+}
+
+enum IdempotencyStatus {
+    Started,
+    InFlight,
+    Replay(CachedResponse),
+}
+
+/// Remembers responses for `Idempotency-Key` requests, scoped by API token and
+/// route so a key reused on another endpoint never replays the wrong response.
+struct IdempotencyCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<IdempotencyCacheKey, IdempotencyEntry>>,
+}
+
+type IdempotencyCacheKey = (String, &'static str, String);
+
+impl IdempotencyCache {
+    fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn begin(&self, scope: &str, route: &'static str, key: &str) -> IdempotencyStatus {
+        let mut entries = self.entries.lock().expect("idempotency cache poisoned");
+        let now = Instant::now();
+        entries.retain(|_, entry| match entry {
+            IdempotencyEntry::InFlight => true,
+            IdempotencyEntry::Completed { stored_at, .. } => {
+                now.duration_since(*stored_at) < self.ttl
+            }
+        });
+
+        let cache_key = (scope.to_string(), route, key.to_string());
+        match entries.get(&cache_key) {
+            Some(IdempotencyEntry::InFlight) => return IdempotencyStatus::InFlight,
+            Some(IdempotencyEntry::Completed { response, .. }) => {
+                return IdempotencyStatus::Replay(response.clone());
+            }
+            None => {}
+        }
+
+        while entries.len() >= self.max_entries {
+            let oldest = entries
+                .iter()
+                .filter_map(|(key, entry)| match entry {
+                    IdempotencyEntry::Completed { stored_at, .. } => {
+                        Some((key.clone(), *stored_at))
+                    }
+                    IdempotencyEntry::InFlight => None,
+                })
+                .min_by_key(|(_, stored_at)| *stored_at)
+                .map(|(key, _)| key);
+            match oldest {
+                Some(key) => {
+                    entries.remove(&key);
+                }
+                None => break,
+            }
+        }
+
+        entries.insert(cache_key, IdempotencyEntry::InFlight);
+        IdempotencyStatus::Started
+    }
+
+    fn complete(&self, scope: &str, route: &'static str, key: &str, response: CachedResponse) {
+        let mut entries = self.entries.lock().expect("idempotency cache poisoned");
+        entries.insert(
+            (scope.to_string(), route, key.to_string()),
+            IdempotencyEntry::Completed {
+                response,
+                stored_at: Instant::now(),
+            },
+        );
+    }
+
+    fn abandon(&self, scope: &str, route: &'static str, key: &str) {
+        let mut entries = self.entries.lock().expect("idempotency cache poisoned");
+        entries.remove(&(scope.to_string(), route, key.to_string()));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EventCounts {
+    events: u64,
+    turns_completed: u64,
+    errors: u64,
+}
+
+impl EventCounts {
+    fn add(&mut self, kind: EventKind) {
+        self.events += 1;
+        match kind {
+            EventKind::TurnCompleted => self.turns_completed += 1,
+            EventKind::Error => self.errors += 1,
+            EventKind::Other => {}
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EventKind {
+    TurnCompleted,
+    Error,
+    Other,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsBucket {
+    hour_start: i64,
+    #[serde(flatten)]
+    counts: EventCounts,
+}
+
+#[derive(Default)]
+struct WorkspaceStats {
+    buckets: VecDeque<StatsBucket>,
+    totals: EventCounts,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkspaceStatsSnapshot {
+    workspace_id: String,
+    totals: EventCounts,
+    buckets: Vec<StatsBucket>,
+}
+
+/// Per-workspace event counters fed by the shared daemon event reader. Hourly
+/// buckets cover the last `STATS_BUCKET_COUNT` hours; totals are kept since start.
+#[derive(Default)]
+struct EventStats {
+    workspaces: Mutex<HashMap<String, WorkspaceStats>>,
+}
+
+impl EventStats {
+    fn record(&self, workspace_id: &str, kind: EventKind, now_secs: i64) {
+        let hour_start = now_secs - now_secs.rem_euclid(STATS_BUCKET_SECS);
+        let mut workspaces = self.workspaces.lock().expect("event stats poisoned");
+        let stats = workspaces.entry(workspace_id.to_string()).or_default();
+        stats.totals.add(kind);
+        match stats.buckets.back_mut() {
+            Some(bucket) if bucket.hour_start == hour_start => bucket.counts.add(kind),
+            _ => {
+                let mut counts = EventCounts::default();
+                counts.add(kind);
+                stats.buckets.push_back(StatsBucket { hour_start, counts });
+            }
+        }
+        prune_stats_buckets(&mut stats.buckets, now_secs);
+    }
+
+    fn snapshot(&self, workspace_id: Option<&str>, now_secs: i64) -> Vec<WorkspaceStatsSnapshot> {
+        let mut workspaces = self.workspaces.lock().expect("event stats poisoned");
+        let mut snapshots: Vec<WorkspaceStatsSnapshot> = workspaces
+            .iter_mut()
+            .filter(|(id, _)| workspace_id.is_none_or(|wanted| wanted == id.as_str()))
+            .map(|(id, stats)| {
+                prune_stats_buckets(&mut stats.buckets, now_secs);
+                WorkspaceStatsSnapshot {
+                    workspace_id: id.clone(),
+                    totals: stats.totals,
+                    buckets: stats.buckets.iter().cloned().collect(),
+                }
+            })
+            .collect();
+        snapshots.sort_by(|a, b| a.workspace_id.cmp(&b.workspace_id));
+        snapshots
+    }
+
+    fn totals(&self) -> Vec<(String, EventCounts)> {
+        let workspaces = self.workspaces.lock().expect("event stats poisoned");
+        let mut totals: Vec<(String, EventCounts)> = workspaces
+            .iter()
+            .map(|(id, stats)| (id.clone(), stats.totals))
+            .collect();
+        totals.sort_by(|a, b| a.0.cmp(&b.0));
+        totals
+    }
+}
+
+fn prune_stats_buckets(buckets: &mut VecDeque<StatsBucket>, now_secs: i64) {
+    let oldest = now_secs - STATS_BUCKET_SECS * STATS_BUCKET_COUNT as i64;
+    while buckets
+        .front()
+        .is_some_and(|bucket| bucket.hour_start <= oldest)
+    {
+        buckets.pop_front();
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuditEntry {
+    at: String,
+    peer: Option<String>,
+    route: String,
+    outcome: &'static str,
+    reason: Option<&'static str>,
+}
+
+/// Bounded log of authentication outcomes plus the per-peer failure history
+/// used to throttle token guessing.
+struct AuthAudit {
+    entries: Mutex<VecDeque<AuditEntry>>,
+    failures: Mutex<HashMap<IpAddr, VecDeque<Instant>>>,
+}
+
+impl AuthAudit {
+    fn new() -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(AUDIT_LOG_CAPACITY)),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn record(
+        &self,
+        peer: Option<IpAddr>,
+        route: &str,
+        outcome: &'static str,
+        reason: Option<&'static str>,
+    ) {
+        let entry = AuditEntry {
+            at: chrono::Utc::now().to_rfc3339(),
+            peer: peer.map(|ip| ip.to_string()),
+            route: route.to_string(),
+            outcome,
+            reason,
+        };
+        if outcome != "success" {
+            eprintln!(
+                "auth audit: {outcome} peer={} route={route} reason={}",
+                entry.peer.as_deref().unwrap_or("-"),
+                reason.unwrap_or("-"),
+            );
+        }
+        let mut entries = self.entries.lock().expect("audit log poisoned");
+        if entries.len() == AUDIT_LOG_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    fn record_failure(
+        &self,
+        peer: Option<IpAddr>,
+        route: &str,
+        reason: &'static str,
+        now: Instant,
+    ) {
+        self.record(peer, route, "failure", Some(reason));
+        // Only wrong guesses count toward the lockout. A client that has not
+        // entered a token yet (the console on first load) must not lock itself out.
+        if reason != "invalid_token" {
+            return;
+        }
+        let Some(ip) = peer else {
+            return;
+        };
+        let mut failures = self.failures.lock().expect("auth failures poisoned");
+        failures.entry(ip).or_default().push_back(now);
+        failures.retain(|_, attempts| {
+            while attempts
+                .front()
+                .is_some_and(|at| now.duration_since(*at) >= AUTH_FAILURE_WINDOW)
+            {
+                attempts.pop_front();
+            }
+            !attempts.is_empty()
+        });
+    }
+
+    /// Returns how long `ip` must wait when it has hit the failure limit.
+    fn throttle(&self, ip: IpAddr, now: Instant) -> Option<Duration> {
+        let failures = self.failures.lock().expect("auth failures poisoned");
+        let mut recent = failures
+            .get(&ip)?
+            .iter()
+            .filter(|at| now.duration_since(**at) < AUTH_FAILURE_WINDOW);
+        let oldest = recent.next()?;
+        if recent.count() + 1 < AUTH_FAILURE_LIMIT {
+            return None;
+        }
+        Some(AUTH_FAILURE_WINDOW.saturating_sub(now.duration_since(*oldest)))
+    }
+
+    /// Newest entries first.
+    fn recent(&self, limit: usize) -> Vec<AuditEntry> {
+        let entries = self.entries.lock().expect("audit log poisoned");
+        entries.iter().rev().take(limit).cloned().collect()
     }
 }
 
@@ -97,6 +565,17 @@ struct ListThreadsQuery {
     sort_key: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct AuditQuery {
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StatsQuery {
+    workspace_id: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct StartThreadRequest {
@@ -110,6 +589,23 @@ struct ResumeThreadRequest {
     thread_id: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteThreadRequest {
+    workspace_id: String,
+    thread_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InterruptThreadRequest {
+    workspace_id: String,
+    thread_id: String,
+    /// Taken from the `turn/started` event. Required: the daemon has no way to
+    /// interrupt a turn it has not reported yet.
+    turn_id: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct SendMessageRequest {
@@ -123,6 +619,16 @@ struct SendMessageRequest {
     collaboration_mode: Option<Value>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportThreadRequest {
+    workspace_id: String,
+    title: Option<String>,
+    #[serde(default)]
+    messages: Vec<TranscriptMessage>,
+    thread: Option<Value>,
+}
+
 #[derive(Debug, Serialize)]
 struct ThreadListResponse {
     workspace_id: String,
@@ -144,118 +650,479 @@ struct WorkspaceDrawingSnapshot {
     error: Option<String>,
 }
 
-fn usage() -> String {
-    format!(
-        "USAGE:\n  codex-monitor-web-gateway [--listen <addr>] [--daemon <addr>] [--daemon-token <token>] [--api-token <token> | --insecure-no-auth]\n\n\
-OPTIONS:\n  --listen <addr>          Bind address for browser clients (default: {DEFAULT_WEB_LISTEN_ADDR})\n  --daemon <addr>          codex-monitor-daemon address (default: {DEFAULT_DAEMON_ADDR})\n  --daemon-token <token>   Token used for daemon auth (or CODEX_MONITOR_DAEMON_TOKEN)\n  --api-token <token>      Token required from browser clients (or CODEX_MONITOR_WEB_TOKEN)\n  --insecure-no-auth       Disable browser auth (LAN dev only)\n  -h, --help               Show this help\n"
-    )
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportWorkspaceQuery {
+    workspace_id: String,
+    max_threads: Option<usize>,
 }
 
-fn parse_args() -> Result<GatewayConfig, String> {
-    let mut listen = DEFAULT_WEB_LISTEN_ADDR
-        .parse::<SocketAddr>()
-        .expect("default listen addr must parse");
-    let mut daemon_addr = DEFAULT_DAEMON_ADDR.to_string();
-    let mut daemon_token = env::var("CODEX_MONITOR_DAEMON_TOKEN")
-        .ok()
-        .map(|value| value.trim().to_string())
-        .filter(|value| !value.is_empty());
-    let mut api_token = env::var("CODEX_MONITOR_WEB_TOKEN")
-        .ok()
-        .map(|value| value.trim().to_string())
-        .filter(|value| !value.is_empty());
-    let mut insecure_no_auth = false;
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportFailure {
+    thread_id: String,
+    error: String,
+}
 
-    let mut args = env::args().skip(1);
-    while let Some(arg) = args.next() {
-        match arg.as_str() {
-            "-h" | "--help" => {
-                return Err(usage());
-            }
-            "--listen" => {
-                let value = args.next().ok_or("--listen requires a value")?;
-                listen = value
-                    .parse::<SocketAddr>()
-                    .map_err(|error| format!("invalid listen address `{value}`: {error}"))?;
-            }
-            "--daemon" => {
-                let value = args.next().ok_or("--daemon requires a value")?;
-                daemon_addr = value.trim().to_string();
-                if daemon_addr.is_empty() {
-                    return Err("--daemon requires a non-empty value".to_string());
-                }
-            }
-            "--daemon-token" => {
-                let value = args.next().ok_or("--daemon-token requires a value")?;
-                daemon_token = Some(value);
-            }
-            "--api-token" => {
-                let value = args.next().ok_or("--api-token requires a value")?;
-                api_token = Some(value);
-            }
-            "--insecure-no-auth" => {
-                insecure_no_auth = true;
-            }
-            other => {
-                return Err(format!("unknown option: {other}"));
-            }
-        }
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportManifest {
+    workspace: Value,
+    exported_at: String,
+    gateway_version: &'static str,
+    daemon_version: Option<String>,
+    thread_count: usize,
+    exported_count: usize,
+    truncated: bool,
+    failures: Vec<ExportFailure>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DrawingsQuery {
+    /// Thread pages to follow per workspace, clamped to `MAX_DRAWINGS_DEPTH`.
+    depth: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThreadFeedQuery {
+    limit: Option<usize>,
+    cursor: Option<String>,
+}
+
+/// Position in the merged feed ordering: newest first, ties broken by
+/// workspace id and then thread id so every thread has a unique slot.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct FeedKey {
+    updated_at: i64,
+    workspace_id: String,
+    thread_id: String,
+}
+
+impl Ord for FeedKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .updated_at
+            .cmp(&self.updated_at)
+            .then_with(|| self.workspace_id.cmp(&other.workspace_id))
+            .then_with(|| self.thread_id.cmp(&other.thread_id))
     }
+}
 
-    if !insecure_no_auth {
-        if api_token.is_none() {
-            return Err(
-                "Missing --api-token (or set CODEX_MONITOR_WEB_TOKEN). Use --insecure-no-auth for local dev only."
-                    .to_string(),
-            );
-        }
-    } else {
-        api_token = None;
+impl PartialOrd for FeedKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
+}
 
-    Ok(GatewayConfig {
-        listen,
-        daemon_addr,
-        daemon_token,
-        api_token,
-    })
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct FeedPosition {
+    cursor: Option<String>,
+    #[serde(default)]
+    done: bool,
 }
 
-fn normalize_token(token: Option<&str>) -> Option<&str> {
-    token.and_then(|value| {
-        let trimmed = value.trim();
-        if trimmed.is_empty() {
-            None
-        } else {
-            Some(trimmed)
-        }
-    })
+/// Opaque `/api/threads/all` cursor. `after` is the last entry already
+/// returned; `positions` holds the daemon page each workspace resumes from.
+/// Entries at or before `after` are skipped on re-read, so threads that move
+/// to the top of a workspace between requests are neither repeated nor allowed
+/// to shift other threads out of the feed.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct FeedCursor {
+    after: Option<FeedKey>,
+    #[serde(default)]
+    positions: BTreeMap<String, FeedPosition>,
 }
 
-fn extract_request_token<'a>(
-    headers: &'a HeaderMap,
-    query_token: Option<&'a str>,
-) -> Option<&'a str> {
-    if let Some(auth_value) = headers
-        .get(header::AUTHORIZATION)
-        .and_then(|value| value.to_str().ok())
-    {
-        if let Some(value) = auth_value.strip_prefix(AUTH_HEADER_PREFIX) {
-            if let Some(token) = normalize_token(Some(value)) {
-                return Some(token);
-            }
-        }
+impl FeedCursor {
+    fn decode(raw: &str) -> Result<Self, GatewayError> {
+        URL_SAFE_NO_PAD
+            .decode(raw.trim())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| GatewayError::bad_request("`cursor` is not a valid feed cursor"))
     }
 
-    if let Some(token) = headers
-        .get("x-codex-monitor-token")
-        .and_then(|value| value.to_str().ok())
-        .and_then(|value| normalize_token(Some(value)))
-    {
-        return Some(token);
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
     }
-
-    normalize_token(query_token)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FeedWarning {
+    workspace_id: String,
+    error: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThreadFeedResponse {
+    threads: Vec<Value>,
+    next_cursor: Option<String>,
+    warnings: Vec<FeedWarning>,
+}
+
+/// One workspace's side of the merge: the daemon page currently buffered and
+/// the cursor for the page after it.
+#[derive(Debug)]
+struct FeedSource {
+    workspace_id: String,
+    workspace_name: Value,
+    /// Normalized workspace path; only threads with this `cwd` belong here.
+    workspace_path: String,
+    page_cursor: Option<String>,
+    next_cursor: Option<String>,
+    buffer: VecDeque<(FeedKey, Value)>,
+    loaded: bool,
+    failed: bool,
+    /// Pages read during this request that left nothing to buffer.
+    empty_pages: usize,
+}
+
+impl FeedSource {
+    fn needs_fetch(&self) -> bool {
+        !self.failed
+            && self.buffer.is_empty()
+            && self.empty_pages < MAX_FEED_EMPTY_PAGES
+            && (!self.loaded || self.next_cursor.is_some())
+    }
+
+    /// Gave up scanning for this request with pages still left to read.
+    fn stalled(&self) -> bool {
+        !self.failed
+            && self.buffer.is_empty()
+            && self.empty_pages >= MAX_FEED_EMPTY_PAGES
+            && self.next_cursor.is_some()
+    }
+
+    fn position(&self) -> FeedPosition {
+        if self.loaded && self.buffer.is_empty() && !self.failed {
+            FeedPosition {
+                cursor: self.next_cursor.clone(),
+                done: self.next_cursor.is_none(),
+            }
+        } else {
+            FeedPosition {
+                cursor: self.page_cursor.clone(),
+                done: false,
+            }
+        }
+    }
+}
+
+fn usage() -> String {
+    format!(
+        "USAGE:\n  codex-monitor-web-gateway [--config <path>] [--listen <addr>] [--daemon <addr>] [--daemon-token <token>] [--daemon-pool-size <n>] [--daemon-timeout <secs>] [--ws-reconnect-attempts <n>] [--api-token <token> | --insecure-no-auth] [--audit-auth-success] [--max-response-bytes <n>] [--max-export-thread-bytes <n>]\n\n\
+OPTIONS:\n  --config <path>          TOML file with any of the options below, keyed by flag name in snake_case\n                           (flags override the file, which overrides env vars)\n  --listen <addr>          Bind address for browser clients (default: {DEFAULT_WEB_LISTEN_ADDR})\n  --daemon <addr>          codex-monitor-daemon address (default: {DEFAULT_DAEMON_ADDR})\n  --daemon-token <token>   Token used for daemon auth (or CODEX_MONITOR_DAEMON_TOKEN)\n  --daemon-pool-size <n>   Idle daemon connections kept open for reuse (default: {DEFAULT_DAEMON_POOL_SIZE})\n  --daemon-timeout <secs>  Seconds to wait for a daemon RPC response (default: {})\n  --ws-reconnect-attempts <n>\n                           Daemon reconnects tried before closing /ws/events clients (default: {DEFAULT_WS_RECONNECT_ATTEMPTS})\n  --api-token <token>      Token required from browser clients (or CODEX_MONITOR_WEB_TOKEN)\n  --insecure-no-auth       Disable browser auth (LAN dev only)\n  --audit-auth-success     Also record accepted tokens in the auth audit log\n  --max-response-bytes <n> Largest thread/RPC result returned as JSON (default: {DEFAULT_MAX_RESPONSE_BYTES})\n  --max-export-thread-bytes <n>\n                           Largest single thread included in a workspace export (default: {DEFAULT_MAX_EXPORT_THREAD_BYTES})\n  -h, --help               Show this help\n",
+        DEFAULT_DAEMON_TIMEOUT.as_secs()
+    )
+}
+
+/// Gateway options from one source: CLI flags, a `--config` file, or env
+/// vars. File keys are the long flag names in snake_case.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GatewaySettings {
+    listen: Option<SocketAddr>,
+    daemon: Option<String>,
+    daemon_token: Option<String>,
+    daemon_pool_size: Option<usize>,
+    /// Seconds, like `--daemon-timeout`.
+    daemon_timeout: Option<u64>,
+    ws_reconnect_attempts: Option<u32>,
+    api_token: Option<String>,
+    insecure_no_auth: Option<bool>,
+    audit_auth_success: Option<bool>,
+    max_response_bytes: Option<usize>,
+    max_export_thread_bytes: Option<usize>,
+}
+
+impl GatewaySettings {
+    fn from_env(env_var: impl Fn(&str) -> Option<String>) -> Self {
+        let read = |name| {
+            env_var(name)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        Self {
+            daemon_token: read("CODEX_MONITOR_DAEMON_TOKEN"),
+            api_token: read("CODEX_MONITOR_WEB_TOKEN"),
+            ..Self::default()
+        }
+    }
+
+    fn from_file(path: &str) -> Result<Self, String> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|error| format!("failed to read config file `{path}`: {error}"))?;
+        Self::from_toml(&raw).map_err(|error| format!("invalid config file `{path}`: {error}"))
+    }
+
+    fn from_toml(raw: &str) -> Result<Self, String> {
+        let mut settings: Self = toml::from_str(raw).map_err(|error| error.to_string())?;
+        // Blank tokens count as unset, as they do in env vars, so they cannot
+        // shadow a token from the environment.
+        let normalize = |token: Option<String>| {
+            token
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        settings.api_token = normalize(settings.api_token);
+        settings.daemon_token = normalize(settings.daemon_token);
+        if settings
+            .daemon
+            .as_deref()
+            .is_some_and(|daemon| daemon.trim().is_empty())
+        {
+            return Err("`daemon` must not be empty".to_string());
+        }
+        if settings.daemon_timeout == Some(0) {
+            return Err("`daemon_timeout` must be greater than zero".to_string());
+        }
+        if settings.max_response_bytes == Some(0) {
+            return Err("`max_response_bytes` must be greater than zero".to_string());
+        }
+        if settings.max_export_thread_bytes == Some(0) {
+            return Err("`max_export_thread_bytes` must be greater than zero".to_string());
+        }
+        Ok(settings)
+    }
+
+    /// Keeps every option set here and takes the rest from `fallback`. An API
+    /// token set here also overrides `insecure_no_auth` from `fallback`.
+    fn or(self, fallback: Self) -> Self {
+        let insecure_no_auth = if self.api_token.is_some() {
+            self.insecure_no_auth
+        } else {
+            self.insecure_no_auth.or(fallback.insecure_no_auth)
+        };
+        Self {
+            listen: self.listen.or(fallback.listen),
+            daemon: self.daemon.or(fallback.daemon),
+            daemon_token: self.daemon_token.or(fallback.daemon_token),
+            daemon_pool_size: self.daemon_pool_size.or(fallback.daemon_pool_size),
+            daemon_timeout: self.daemon_timeout.or(fallback.daemon_timeout),
+            ws_reconnect_attempts: self
+                .ws_reconnect_attempts
+                .or(fallback.ws_reconnect_attempts),
+            api_token: self.api_token.or(fallback.api_token),
+            insecure_no_auth,
+            audit_auth_success: self.audit_auth_success.or(fallback.audit_auth_success),
+            max_response_bytes: self.max_response_bytes.or(fallback.max_response_bytes),
+            max_export_thread_bytes: self
+                .max_export_thread_bytes
+                .or(fallback.max_export_thread_bytes),
+        }
+    }
+
+    fn into_config(self) -> Result<GatewayConfig, String> {
+        let api_token = if self.insecure_no_auth.unwrap_or(false) {
+            None
+        } else {
+            Some(self.api_token.ok_or(
+                "Missing --api-token (or set CODEX_MONITOR_WEB_TOKEN). Use --insecure-no-auth for local dev only.",
+            )?)
+        };
+
+        Ok(GatewayConfig {
+            listen: self.listen.unwrap_or_else(|| {
+                DEFAULT_WEB_LISTEN_ADDR
+                    .parse()
+                    .expect("default listen addr must parse")
+            }),
+            daemon_addr: self
+                .daemon
+                .map(|daemon| daemon.trim().to_string())
+                .unwrap_or_else(|| DEFAULT_DAEMON_ADDR.to_string()),
+            daemon_token: self.daemon_token,
+            daemon_pool_size: self.daemon_pool_size.unwrap_or(DEFAULT_DAEMON_POOL_SIZE),
+            daemon_timeout: self
+                .daemon_timeout
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_DAEMON_TIMEOUT),
+            ws_reconnect_attempts: self
+                .ws_reconnect_attempts
+                .unwrap_or(DEFAULT_WS_RECONNECT_ATTEMPTS),
+            api_token,
+            audit_successes: self.audit_auth_success.unwrap_or(false),
+            max_response_bytes: self
+                .max_response_bytes
+                .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES),
+            max_export_thread_bytes: self
+                .max_export_thread_bytes
+                .unwrap_or(DEFAULT_MAX_EXPORT_THREAD_BYTES),
+        })
+    }
+}
+
+fn parse_args() -> Result<GatewayConfig, String> {
+    parse_args_from(env::args().skip(1), |name| env::var(name).ok())
+}
+
+fn parse_args_from(
+    args: impl IntoIterator<Item = String>,
+    env_var: impl Fn(&str) -> Option<String>,
+) -> Result<GatewayConfig, String> {
+    let mut cli = GatewaySettings::default();
+    let mut config_path = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                return Err(usage());
+            }
+            "--config" => {
+                let value = args.next().ok_or("--config requires a value")?;
+                config_path = Some(value);
+            }
+            "--listen" => {
+                let value = args.next().ok_or("--listen requires a value")?;
+                cli.listen = Some(
+                    value
+                        .parse::<SocketAddr>()
+                        .map_err(|error| format!("invalid listen address `{value}`: {error}"))?,
+                );
+            }
+            "--daemon" => {
+                let value = args.next().ok_or("--daemon requires a value")?;
+                if value.trim().is_empty() {
+                    return Err("--daemon requires a non-empty value".to_string());
+                }
+                cli.daemon = Some(value);
+            }
+            "--daemon-token" => {
+                let value = args.next().ok_or("--daemon-token requires a value")?;
+                cli.daemon_token = Some(value);
+            }
+            "--daemon-pool-size" => {
+                let value = args.next().ok_or("--daemon-pool-size requires a value")?;
+                cli.daemon_pool_size = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| format!("invalid --daemon-pool-size `{value}`"))?,
+                );
+            }
+            "--daemon-timeout" => {
+                let value = args.next().ok_or("--daemon-timeout requires a value")?;
+                let secs = value
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|secs| *secs > 0)
+                    .ok_or_else(|| format!("invalid --daemon-timeout `{value}`"))?;
+                cli.daemon_timeout = Some(secs);
+            }
+            "--ws-reconnect-attempts" => {
+                let value = args
+                    .next()
+                    .ok_or("--ws-reconnect-attempts requires a value")?;
+                cli.ws_reconnect_attempts = Some(
+                    value
+                        .trim()
+                        .parse::<u32>()
+                        .map_err(|_| format!("invalid --ws-reconnect-attempts `{value}`"))?,
+                );
+            }
+            "--api-token" => {
+                let value = args.next().ok_or("--api-token requires a value")?;
+                cli.api_token = Some(value);
+            }
+            "--insecure-no-auth" => {
+                cli.insecure_no_auth = Some(true);
+            }
+            "--audit-auth-success" => {
+                cli.audit_auth_success = Some(true);
+            }
+            "--max-response-bytes" => {
+                let value = args.next().ok_or("--max-response-bytes requires a value")?;
+                let bytes = value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|bytes| *bytes > 0)
+                    .ok_or_else(|| format!("invalid --max-response-bytes `{value}`"))?;
+                cli.max_response_bytes = Some(bytes);
+            }
+            "--max-export-thread-bytes" => {
+                let value = args
+                    .next()
+                    .ok_or("--max-export-thread-bytes requires a value")?;
+                let bytes = value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|bytes| *bytes > 0)
+                    .ok_or_else(|| format!("invalid --max-export-thread-bytes `{value}`"))?;
+                cli.max_export_thread_bytes = Some(bytes);
+            }
+            other => {
+                return Err(format!("unknown option: {other}"));
+            }
+        }
+    }
+
+    let file = match config_path {
+        Some(path) => GatewaySettings::from_file(&path)?,
+        None => GatewaySettings::default(),
+    };
+    cli.or(file)
+        .or(GatewaySettings::from_env(env_var))
+        .into_config()
+}
+
+fn normalize_token(token: Option<&str>) -> Option<&str> {
+    token.and_then(|value| {
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            None
+        } else {
+            Some(trimmed)
+        }
+    })
+}
+
+fn extract_request_token<'a>(
+    headers: &'a HeaderMap,
+    query_token: Option<&'a str>,
+) -> Option<&'a str> {
+    if let Some(auth_value) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+    {
+        if let Some(value) = auth_value.strip_prefix(AUTH_HEADER_PREFIX) {
+            if let Some(token) = normalize_token(Some(value)) {
+                return Some(token);
+            }
+        }
+    }
+
+    if let Some(token) = headers
+        .get("x-codex-monitor-token")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| normalize_token(Some(value)))
+    {
+        return Some(token);
+    }
+
+    normalize_token(query_token)
+}
+
+/// Browsers cannot set headers on a WebSocket handshake, so `/ws/events` also accepts
+/// the token as a `codex-monitor.token.<token>` subprotocol offered next to `WS_PROTOCOL`.
+fn extract_ws_protocol_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|protocol| {
+            normalize_token(protocol.trim().strip_prefix(WS_TOKEN_PROTOCOL_PREFIX))
+        })
 }
 
 fn authorize_request(
@@ -269,15 +1136,151 @@ fn authorize_request(
 
     let Some(provided_token) = extract_request_token(headers, query_token) else {
         return Err(GatewayError::unauthorized(
+            "missing_token",
             "missing API token (expected Authorization: Bearer <token>)",
         ));
     };
 
-    if provided_token == expected_token {
+    if constant_time_eq(provided_token.as_bytes(), expected_token.as_bytes()) {
         return Ok(());
     }
 
-    Err(GatewayError::unauthorized("invalid API token"))
+    Err(GatewayError::unauthorized(
+        "invalid_token",
+        "invalid API token",
+    ))
+}
+
+/// Compares secrets without exiting early on the first differing byte.
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    let mut diff = left.len() ^ right.len();
+    for index in 0..left.len().max(right.len()) {
+        let a = left.get(index).copied().unwrap_or(0);
+        let b = right.get(index).copied().unwrap_or(0);
+        diff |= usize::from(a ^ b);
+    }
+    diff == 0
+}
+
+fn presents_credentials(request: &Request) -> bool {
+    let headers = request.headers();
+    extract_request_token(headers, None).is_some()
+        || extract_ws_protocol_token(headers).is_some()
+        || request
+            .uri()
+            .query()
+            .is_some_and(|query| query.split('&').any(|pair| pair.starts_with("token=")))
+}
+
+/// Records auth failures (and optionally successes) with the peer address and
+/// route, and turns away peers that keep failing until their window expires.
+async fn audit_auth(State(state): State<GatewayState>, request: Request, next: Next) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip());
+    let route = request.uri().path().to_string();
+    let now = Instant::now();
+
+    if let Some(retry_after) = peer.and_then(|ip| state.audit.throttle(ip, now)) {
+        state
+            .audit
+            .record(peer, &route, "throttled", Some("too_many_failures"));
+        let mut response =
+            GatewayError::too_many_requests("too many failed authentication attempts")
+                .into_response();
+        if let Ok(value) = HeaderValue::from_str(&retry_after.as_secs().max(1).to_string()) {
+            response.headers_mut().insert(header::RETRY_AFTER, value);
+        }
+        return response;
+    }
+
+    let audit_success = state.config.audit_successes
+        && state.config.api_token.is_some()
+        && presents_credentials(&request);
+    let response = next.run(request).await;
+    match response.extensions().get::<AuthRejection>() {
+        Some(AuthRejection(reason)) => state.audit.record_failure(peer, &route, reason, now),
+        None if audit_success => state.audit.record(peer, &route, "success", None),
+        None => {}
+    }
+    response
+}
+
+fn parse_idempotency_key(headers: &HeaderMap) -> Result<Option<String>, GatewayError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let key = value
+        .to_str()
+        .map_err(|_| GatewayError::bad_request("`Idempotency-Key` must be visible ASCII"))?
+        .trim();
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(GatewayError::bad_request(format!(
+            "`Idempotency-Key` must be 1-{MAX_IDEMPOTENCY_KEY_LEN} characters"
+        )));
+    }
+    if !key.bytes().all(|byte| byte.is_ascii_graphic()) {
+        return Err(GatewayError::bad_request(
+            "`Idempotency-Key` must be visible ASCII",
+        ));
+    }
+    Ok(Some(key.to_string()))
+}
+
+/// Runs `operation` at most once per idempotency key. The operation is spawned so a
+/// client that disconnects mid-request can still retry and receive the stored result.
+async fn run_idempotent<F>(
+    state: &GatewayState,
+    headers: &HeaderMap,
+    route: &'static str,
+    idempotency_key: Option<String>,
+    operation: F,
+) -> Result<Response, GatewayError>
+where
+    F: Future<Output = Result<Value, GatewayError>> + Send + 'static,
+{
+    let Some(key) = idempotency_key else {
+        return operation.await.map(|body| Json(body).into_response());
+    };
+    let scope = extract_request_token(headers, None)
+        .unwrap_or_default()
+        .to_string();
+
+    match state.idempotency.begin(&scope, route, &key) {
+        IdempotencyStatus::Started => {}
+        IdempotencyStatus::InFlight => {
+            return Err(GatewayError::conflict(
+                "request_in_flight",
+                "a request with this Idempotency-Key is still executing",
+            ));
+        }
+        IdempotencyStatus::Replay(cached) => return Ok(cached.into_replay_response()),
+    }
+
+    let cache = state.idempotency.clone();
+    let task_scope = scope.clone();
+    let task_key = key.clone();
+    let task = tokio::spawn(async move {
+        let outcome = operation.await;
+        cache.complete(
+            &task_scope,
+            route,
+            &task_key,
+            CachedResponse::from_outcome(&outcome),
+        );
+        outcome
+    });
+
+    match task.await {
+        Ok(outcome) => outcome.map(|body| Json(body).into_response()),
+        Err(error) => {
+            state.idempotency.abandon(&scope, route, &key);
+            Err(GatewayError::daemon(format!(
+                "request task failed: {error}"
+            )))
+        }
+    }
 }
 
 fn parse_error_message(message: &Value) -> String {
@@ -298,7 +1301,80 @@ fn is_event_notification(message: &Value) -> bool {
             .unwrap_or(false)
 }
 
-fn peel_result_envelope<'a>(value: &'a Value) -> &'a Value {
+fn unix_now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Maps a daemon notification to its workspace and the counter it feeds.
+fn classify_event(message: &Value) -> Option<(String, EventKind)> {
+    let params = message.get("params")?;
+    let workspace_id = params
+        .get("workspace_id")
+        .or_else(|| params.get("workspaceId"))
+        .and_then(Value::as_str)?
+        .to_string();
+    let kind = match message.get("method").and_then(Value::as_str) {
+        Some("app-server-event") => match params
+            .get("message")
+            .and_then(|inner| inner.get("method"))
+            .and_then(Value::as_str)
+        {
+            Some("turn/completed") => EventKind::TurnCompleted,
+            Some("error") => EventKind::Error,
+            _ => EventKind::Other,
+        },
+        _ => EventKind::Other,
+    };
+    Some((workspace_id, kind))
+}
+
+fn push_metric(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    totals: &[(String, EventCounts)],
+    value: impl Fn(&EventCounts) -> u64,
+) {
+    output.push_str(&format!("# HELP {name} {help}\n# TYPE {name} counter\n"));
+    for (workspace_id, counts) in totals {
+        let label = workspace_id.replace('\\', "\\\\").replace('"', "\\\"");
+        output.push_str(&format!(
+            "{name}{{workspace_id=\"{label}\"}} {}\n",
+            value(counts)
+        ));
+    }
+}
+
+fn render_metrics(totals: &[(String, EventCounts)]) -> String {
+    let mut output = String::new();
+    push_metric(
+        &mut output,
+        "codex_gateway_events_total",
+        "Daemon notifications seen by the gateway.",
+        totals,
+        |counts| counts.events,
+    );
+    push_metric(
+        &mut output,
+        "codex_gateway_turns_completed_total",
+        "Completed turns seen by the gateway.",
+        totals,
+        |counts| counts.turns_completed,
+    );
+    push_metric(
+        &mut output,
+        "codex_gateway_errors_total",
+        "Error notifications seen by the gateway.",
+        totals,
+        |counts| counts.errors,
+    );
+    output
+}
+
+fn peel_result_envelope(value: &Value) -> &Value {
     if let Some(inner) = value.get("result") {
         if inner.is_object() {
             return inner;
@@ -330,6 +1406,23 @@ fn parse_thread_page(value: &Value) -> (Vec<Value>, Option<String>) {
     (threads, next_cursor)
 }
 
+/// Same normalization as the desktop app's `normalizeRootPath`: backslashes
+/// become slashes and trailing slashes are dropped.
+fn normalize_root_path(path: &str) -> String {
+    path.replace('\\', "/").trim_end_matches('/').to_string()
+}
+
+/// Workspaces share one Codex home, so `list_threads` pages through every
+/// project's threads. A workspace owns those whose `cwd` is its path;
+/// `workspace_path` must already be normalized.
+fn thread_in_workspace(thread: &Value, workspace_path: &str) -> bool {
+    let cwd = thread
+        .get("cwd")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    normalize_root_path(cwd) == workspace_path
+}
+
 fn parse_thread_id_from_start_response(value: &Value) -> Option<String> {
     let response = peel_result_envelope(value);
     let response = peel_result_envelope(response);
@@ -348,71 +1441,427 @@ fn parse_thread_id_from_start_response(value: &Value) -> Option<String> {
         })
 }
 
-type DaemonLines = tokio::io::Lines<BufReader<OwnedReadHalf>>;
-
-async fn connect_daemon_stream(config: &GatewayConfig) -> Result<TcpStream, String> {
-    TcpStream::connect(config.daemon_addr.clone())
-        .await
-        .map_err(|error| {
-            format!(
-                "failed to connect to daemon at {}: {error}",
-                config.daemon_addr
-            )
+/// Reads a thread's last-activity time as a sortable integer. Numeric values
+/// are used as-is; RFC 3339 strings are converted to unix seconds.
+fn thread_updated_at(thread: &Value) -> i64 {
+    ["updatedAt", "updated_at", "createdAt", "created_at"]
+        .iter()
+        .filter_map(|key| thread.get(*key))
+        .find_map(|value| match value {
+            Value::Number(number) => number
+                .as_i64()
+                .or_else(|| number.as_f64().map(|value| value as i64)),
+            Value::String(text) => text.parse::<i64>().ok().or_else(|| {
+                chrono::DateTime::parse_from_rfc3339(text)
+                    .ok()
+                    .map(|time| time.timestamp())
+            }),
+            _ => None,
         })
+        .unwrap_or(0)
 }
 
-async fn send_daemon_request(
-    writer: &mut OwnedWriteHalf,
-    id: u64,
-    method: &str,
-    params: Value,
-) -> Result<(), String> {
-    let payload = serde_json::to_string(&json!({
-        "id": id,
-        "method": method,
-        "params": params,
-    }))
-    .map_err(|error| error.to_string())?;
-
-    writer
-        .write_all(payload.as_bytes())
-        .await
-        .map_err(|error| error.to_string())?;
-    writer
-        .write_all(b"\n")
-        .await
-        .map_err(|error| error.to_string())
+fn thread_title(thread: &Value) -> String {
+    ["name", "title", "preview"]
+        .iter()
+        .filter_map(|key| thread.get(*key).and_then(Value::as_str))
+        .map(str::trim)
+        .find(|value| !value.is_empty())
+        .map(|value| value.lines().next().unwrap_or(value).to_string())
+        .unwrap_or_else(|| "Untitled thread".to_string())
 }
 
-async fn read_daemon_response(lines: &mut DaemonLines, expected_id: u64) -> Result<Value, String> {
-    loop {
-        let line = lines
-            .next_line()
-            .await
-            .map_err(|error| error.to_string())?
-            .ok_or_else(|| "daemon disconnected".to_string())?;
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
+fn user_message_text(item: &Value) -> String {
+    item.get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|input| match input.get("type").and_then(Value::as_str) {
+            Some("text") => input
+                .get("text")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            Some("skill") => input
+                .get("name")
+                .and_then(Value::as_str)
+                .map(|name| format!("${name}")),
+            Some("image") | Some("localImage") => Some("[image]".to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-        let message: Value = serde_json::from_str(trimmed)
-            .map_err(|error| format!("invalid daemon response: {error}"))?;
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+struct TranscriptMessage {
+    role: String,
+    text: String,
+}
 
-        if message.get("id").and_then(Value::as_u64) != Some(expected_id) {
-            continue;
-        }
+/// Flattens a resumed thread into its user/assistant messages, skipping tool items.
+fn thread_transcript(thread: &Value) -> Vec<TranscriptMessage> {
+    thread
+        .get("turns")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|turn| turn.get("items").and_then(Value::as_array))
+        .flatten()
+        .filter_map(|item| {
+            let (role, text) = match item.get("type").and_then(Value::as_str) {
+                Some("userMessage") => ("user", user_message_text(item)),
+                Some("agentMessage") => (
+                    "assistant",
+                    item.get("text")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                ),
+                _ => return None,
+            };
+            let text = text.trim();
+            (!text.is_empty()).then(|| TranscriptMessage {
+                role: role.to_string(),
+                text: text.to_string(),
+            })
+        })
+        .collect()
+}
 
-        if message.get("error").is_some() {
-            return Err(parse_error_message(&message));
-        }
+fn push_transcript_markdown(markdown: &mut String, messages: &[TranscriptMessage]) {
+    for message in messages {
+        let heading = if message.role == "assistant" {
+            "Assistant"
+        } else {
+            "User"
+        };
+        markdown.push_str(&format!("\n## {heading}\n\n{}\n", message.text.trim()));
+    }
+}
 
-        return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+/// Renders the user/assistant messages of a resumed thread as Markdown.
+fn render_thread_markdown(thread: &Value) -> String {
+    let mut markdown = format!("# {}\n\n", thread_title(thread));
+    if let Some(id) = thread.get("id").and_then(Value::as_str) {
+        markdown.push_str(&format!("- Thread: `{id}`\n"));
+    }
+    if let Some(updated_at) = thread.get("updatedAt").filter(|value| !value.is_null()) {
+        markdown.push_str(&format!("- Updated: {updated_at}\n"));
     }
+    push_transcript_markdown(&mut markdown, &thread_transcript(thread));
+    markdown
 }
 
-async fn authenticate_daemon(
-    config: &GatewayConfig,
+/// Resolves the transcript and title of an import request. Explicit `messages`
+/// win; otherwise the raw thread JSON from an export archive is flattened.
+fn import_transcript(
+    request: &ImportThreadRequest,
+) -> Result<(Vec<TranscriptMessage>, Option<String>), GatewayError> {
+    let thread = request.thread.as_ref().map(|value| {
+        let value = peel_result_envelope(peel_result_envelope(value));
+        value.get("thread").unwrap_or(value)
+    });
+    let messages = if request.messages.is_empty() {
+        thread.map(thread_transcript).unwrap_or_default()
+    } else {
+        request.messages.clone()
+    };
+    let title = request
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .map(ToString::to_string)
+        .or_else(|| thread.map(thread_title));
+
+    if messages.is_empty() {
+        return Err(GatewayError::bad_request(
+            "provide a non-empty `messages` array or an exported `thread`",
+        ));
+    }
+    if messages.len() > MAX_IMPORT_MESSAGES {
+        return Err(GatewayError::payload_too_large(format!(
+            "transcript has {} messages; the limit is {MAX_IMPORT_MESSAGES}",
+            messages.len()
+        )));
+    }
+    let mut total_bytes = 0;
+    for (index, message) in messages.iter().enumerate() {
+        if message.role != "user" && message.role != "assistant" {
+            return Err(GatewayError::bad_request(format!(
+                "`messages[{index}].role` must be `user` or `assistant`"
+            )));
+        }
+        if message.text.trim().is_empty() {
+            return Err(GatewayError::bad_request(format!(
+                "`messages[{index}].text` must not be empty"
+            )));
+        }
+        total_bytes += message.text.len();
+    }
+    if total_bytes > MAX_IMPORT_TEXT_BYTES {
+        return Err(GatewayError::payload_too_large(format!(
+            "transcript text is {total_bytes} bytes; the limit is {MAX_IMPORT_TEXT_BYTES}"
+        )));
+    }
+    Ok((messages, title))
+}
+
+/// Builds the single user message that replays an imported transcript. The
+/// daemon can only append live user turns, so prior history is sent as quoted
+/// context rather than as individual items.
+fn render_import_seed(title: Option<&str>, messages: &[TranscriptMessage]) -> String {
+    let mut seed = String::from(
+        "This thread was imported from an exported CodexMonitor transcript. \
+         Treat the conversation below as prior context and wait for my next message.\n",
+    );
+    if let Some(title) = title {
+        seed.push_str(&format!("\n# {title}\n"));
+    }
+    push_transcript_markdown(&mut seed, messages);
+    seed
+}
+
+fn sanitize_file_component(value: &str) -> String {
+    value
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn export_file_stem(index: usize, thread_id: &str) -> String {
+    format!(
+        "threads/{:04}-{}",
+        index + 1,
+        sanitize_file_component(thread_id)
+    )
+}
+
+/// `Write` sink shared with the zip writer; drained after each entry so only one
+/// rendered thread is buffered at a time.
+#[derive(Clone, Default)]
+struct ExportBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl ExportBuffer {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().expect("export buffer poisoned"))
+    }
+}
+
+impl Write for ExportBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .lock()
+            .expect("export buffer poisoned")
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+type ExportChunk = Result<Bytes, std::io::Error>;
+
+struct ExportArchive {
+    zip: ZipWriter<StreamWriter<ExportBuffer>>,
+    buffer: ExportBuffer,
+    tx: mpsc::Sender<ExportChunk>,
+}
+
+impl ExportArchive {
+    fn new(tx: mpsc::Sender<ExportChunk>) -> Self {
+        let buffer = ExportBuffer::default();
+        Self {
+            zip: ZipWriter::new_stream(buffer.clone()),
+            buffer,
+            tx,
+        }
+    }
+
+    async fn add_file(&mut self, name: &str, contents: &[u8]) -> Result<(), String> {
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        self.zip
+            .start_file(name, options)
+            .map_err(|error| error.to_string())?;
+        self.zip
+            .write_all(contents)
+            .map_err(|error| error.to_string())?;
+        self.flush().await
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        let chunk = self.buffer.take();
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        self.tx
+            .send(Ok(Bytes::from(chunk)))
+            .await
+            .map_err(|_| "export client disconnected".to_string())
+    }
+
+    async fn finish(self) -> Result<(), String> {
+        let Self { zip, buffer, tx } = self;
+        zip.finish().map_err(|error| error.to_string())?;
+        let mut archive = Self {
+            zip: ZipWriter::new_stream(ExportBuffer::default()),
+            buffer,
+            tx,
+        };
+        archive.flush().await
+    }
+}
+
+/// Line reader for a daemon connection that fails once a line grows past
+/// `limit` bytes, so an oversized response is rejected while it is read rather
+/// than after it was buffered and parsed. Cancel safe: a partial line stays in
+/// `buf` for the next call.
+struct DaemonLines {
+    reader: BufReader<OwnedReadHalf>,
+    buf: Vec<u8>,
+    limit: usize,
+    exceeded_limit: bool,
+}
+
+impl DaemonLines {
+    fn new(reader: OwnedReadHalf, limit: usize) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            buf: Vec::new(),
+            limit,
+            exceeded_limit: false,
+        }
+    }
+
+    async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        loop {
+            let available = self.reader.fill_buf().await?;
+            if available.is_empty() {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                return Self::decode(std::mem::take(&mut self.buf)).map(Some);
+            }
+
+            let newline = available.iter().position(|byte| *byte == b'\n');
+            let used = newline.map_or(available.len(), |index| index + 1);
+            let line_len = self.buf.len() + newline.unwrap_or(used);
+            if line_len > self.limit {
+                self.exceeded_limit = true;
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "daemon response exceeds the {} byte response limit",
+                        self.limit
+                    ),
+                ));
+            }
+            self.buf.extend_from_slice(&available[..used]);
+            self.reader.consume(used);
+
+            if newline.is_some() {
+                let mut line = std::mem::take(&mut self.buf);
+                line.pop();
+                return Self::decode(line).map(Some);
+            }
+        }
+    }
+
+    fn decode(line: Vec<u8>) -> std::io::Result<String> {
+        String::from_utf8(line)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+    }
+}
+
+async fn connect_daemon_stream(config: &GatewayConfig) -> Result<TcpStream, String> {
+    TcpStream::connect(config.daemon_addr.clone())
+        .await
+        .map_err(|error| {
+            format!(
+                "failed to connect to daemon at {}: {error}",
+                config.daemon_addr
+            )
+        })
+}
+
+async fn send_daemon_request(
+    writer: &mut OwnedWriteHalf,
+    id: u64,
+    method: &str,
+    params: Value,
+) -> Result<(), String> {
+    let mut payload = serde_json::to_string(&json!({
+        "id": id,
+        "method": method,
+        "params": params,
+    }))
+    .map_err(|error| error.to_string())?;
+    // One write per request line: a split write stalls on Nagle's algorithm
+    // once connections are reused.
+    payload.push('\n');
+
+    writer
+        .write_all(payload.as_bytes())
+        .await
+        .map_err(|error| error.to_string())
+}
+
+/// Reads lines until the response with `expected_id` arrives. Errors here mean
+/// the connection itself is unusable.
+async fn read_daemon_message(lines: &mut DaemonLines, expected_id: u64) -> Result<Value, String> {
+    loop {
+        let message = next_daemon_message(lines).await?;
+        if message.get("id").and_then(Value::as_u64) == Some(expected_id) {
+            return Ok(message);
+        }
+    }
+}
+
+async fn next_daemon_message(lines: &mut DaemonLines) -> Result<Value, String> {
+    loop {
+        let line = lines
+            .next_line()
+            .await
+            .map_err(|error| error.to_string())?
+            .ok_or_else(|| "daemon disconnected".to_string())?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        return serde_json::from_str(trimmed)
+            .map_err(|error| format!("invalid daemon response: {error}"));
+    }
+}
+
+/// Daemon request ids are unique for the gateway's lifetime, so a response can
+/// never be mistaken for one sent earlier on the same connection.
+fn next_request_id(ids: &AtomicU64) -> u64 {
+    ids.fetch_add(1, AtomicOrdering::Relaxed)
+}
+
+fn daemon_result(message: &Value) -> Result<Value, String> {
+    if message.get("error").is_some() {
+        return Err(parse_error_message(message));
+    }
+    Ok(message.get("result").cloned().unwrap_or(Value::Null))
+}
+
+async fn read_daemon_response(lines: &mut DaemonLines, expected_id: u64) -> Result<Value, String> {
+    daemon_result(&read_daemon_message(lines, expected_id).await?)
+}
+
+async fn authenticate_daemon(
+    config: &GatewayConfig,
+    request_ids: &AtomicU64,
     writer: &mut OwnedWriteHalf,
     lines: &mut DaemonLines,
 ) -> Result<(), String> {
@@ -420,33 +1869,237 @@ async fn authenticate_daemon(
         return Ok(());
     };
 
-    send_daemon_request(writer, 1, "auth", json!({ "token": token })).await?;
-    let _ = read_daemon_response(lines, 1).await?;
+    let id = next_request_id(request_ids);
+    send_daemon_request(writer, id, "auth", json!({ "token": token })).await?;
+    let _ = read_daemon_response(lines, id).await?;
     Ok(())
 }
 
+/// An authenticated daemon connection. Responses that arrive while waiting
+/// for a different id are held until their own `recv`.
+struct DaemonConn {
+    writer: OwnedWriteHalf,
+    lines: DaemonLines,
+    unclaimed: HashMap<u64, Value>,
+    /// Whether the daemon stopped sending events here. Only such connections
+    /// are parked: an idle one that still receives events is never read, so
+    /// its backlog would grow until the next borrower had to parse it.
+    events_muted: bool,
+}
+
+impl DaemonConn {
+    async fn open(config: &GatewayConfig, request_ids: &AtomicU64) -> Result<Self, String> {
+        let stream = connect_daemon_stream(config).await?;
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = DaemonLines::new(reader, config.max_response_bytes);
+        authenticate_daemon(config, request_ids, &mut writer, &mut lines).await?;
+        let mut conn = Self {
+            writer,
+            lines,
+            unclaimed: HashMap::new(),
+            events_muted: false,
+        };
+        // Daemons that predate `unsubscribe_events` answer with an error; their
+        // connections still work but are closed after each call.
+        let id = next_request_id(request_ids);
+        conn.events_muted = conn
+            .call(id, "unsubscribe_events", Value::Null)
+            .await?
+            .is_ok();
+        Ok(conn)
+    }
+
+    /// Whether a parked connection is still open. Checked before sending, so a
+    /// connection the daemon closed while idle is replaced before any request
+    /// reaches it.
+    fn is_alive(&mut self) -> bool {
+        let mut probe = [0u8; 1];
+        let reader = self.lines.reader.get_mut();
+        match reader.peek(&mut probe).now_or_never() {
+            None => true,
+            Some(Ok(read)) => read > 0,
+            Some(Err(_)) => false,
+        }
+    }
+
+    async fn send(&mut self, id: u64, method: &str, params: Value) -> Result<(), String> {
+        send_daemon_request(&mut self.writer, id, method, params).await
+    }
+
+    /// The outer error means the connection is broken and must be dropped; the
+    /// inner one is an error the daemon returned for this request.
+    async fn recv(&mut self, id: u64) -> Result<Result<Value, String>, String> {
+        if let Some(message) = self.unclaimed.remove(&id) {
+            return Ok(daemon_result(&message));
+        }
+        loop {
+            let message = next_daemon_message(&mut self.lines).await?;
+            match message.get("id").and_then(Value::as_u64) {
+                Some(found) if found == id => return Ok(daemon_result(&message)),
+                Some(found) => {
+                    self.unclaimed.insert(found, message);
+                }
+                None => {}
+            }
+        }
+    }
+
+    async fn call(
+        &mut self,
+        id: u64,
+        method: &str,
+        params: Value,
+    ) -> Result<Result<Value, String>, String> {
+        self.send(id, method, params).await?;
+        self.recv(id).await
+    }
+}
+
+/// Keeps up to `daemon_pool_size` authenticated connections open between RPC
+/// calls. Calls borrow an idle connection or open a new one when none is free.
+struct DaemonPool {
+    config: Arc<GatewayConfig>,
+    request_ids: Arc<AtomicU64>,
+    idle: Mutex<Vec<DaemonConn>>,
+}
+
+impl DaemonPool {
+    fn new(config: Arc<GatewayConfig>, request_ids: Arc<AtomicU64>) -> Self {
+        Self {
+            config,
+            request_ids,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    async fn open(&self) -> Result<DaemonConn, String> {
+        DaemonConn::open(&self.config, &self.request_ids).await
+    }
+
+    /// Borrows an idle connection, skipping any the daemon closed while they
+    /// were parked, or opens a new one.
+    async fn acquire(&self) -> Result<DaemonConn, String> {
+        loop {
+            let idle = self.idle.lock().expect("daemon pool poisoned").pop();
+            let Some(mut conn) = idle else {
+                return self.open().await;
+            };
+            if conn.is_alive() {
+                return Ok(conn);
+            }
+        }
+    }
+
+    fn release(&self, conn: DaemonConn) {
+        if !conn.events_muted {
+            return;
+        }
+        let mut idle = self.idle.lock().expect("daemon pool poisoned");
+        if idle.len() < self.config.daemon_pool_size {
+            idle.push(conn);
+        }
+    }
+}
+
+/// Runs one RPC on a pooled connection. A call that exceeds the daemon timeout
+/// is abandoned together with its connection, so a late response can never be
+/// read by the next borrower.
 async fn call_daemon_rpc(
-    config: &GatewayConfig,
+    daemon: &DaemonPool,
     method: &str,
     params: Value,
 ) -> Result<Value, GatewayError> {
-    let stream = connect_daemon_stream(config)
-        .await
-        .map_err(GatewayError::daemon)?;
-    let (reader, mut writer) = stream.into_split();
-    let mut lines = BufReader::new(reader).lines();
+    let limit = daemon.config.max_response_bytes;
+    call_daemon_rpc_with_limit(daemon, method, params, limit).await
+}
 
-    authenticate_daemon(config, &mut writer, &mut lines)
-        .await
-        .map_err(GatewayError::daemon)?;
+/// Like `call_daemon_rpc`, with a response size limit other than
+/// `max_response_bytes`.
+async fn call_daemon_rpc_with_limit(
+    daemon: &DaemonPool,
+    method: &str,
+    params: Value,
+    limit: usize,
+) -> Result<Value, GatewayError> {
+    let timeout = daemon.config.daemon_timeout;
+    tokio::time::timeout(
+        timeout,
+        call_pooled_daemon_rpc(daemon, method, params, limit),
+    )
+    .await
+    .unwrap_or_else(|_| {
+        Err(GatewayError::daemon_timeout(format!(
+            "daemon did not answer `{method}` within {}s",
+            timeout.as_secs_f32()
+        )))
+    })
+}
 
-    send_daemon_request(&mut writer, 2, method, params)
-        .await
-        .map_err(GatewayError::daemon)?;
+async fn call_pooled_daemon_rpc(
+    daemon: &DaemonPool,
+    method: &str,
+    params: Value,
+    limit: usize,
+) -> Result<Value, GatewayError> {
+    let mut conn = daemon.acquire().await.map_err(GatewayError::daemon)?;
+    conn.lines.limit = limit;
+    // Never resent on failure: once the request is written the daemon may
+    // already have run it, and methods like `send_user_message` must not repeat.
+    let id = next_request_id(&daemon.request_ids);
+    let result = match conn.call(id, method, params).await {
+        Ok(result) => result,
+        Err(error) if conn.lines.exceeded_limit => {
+            return Err(GatewayError::payload_too_large(format!(
+                "{error}; narrow the request with pagination parameters such as `limit`, \
+                 or use /api/workspaces/export to download large threads"
+            )));
+        }
+        Err(error) => return Err(GatewayError::daemon(error)),
+    };
+    conn.lines.limit = daemon.config.max_response_bytes;
+    daemon.release(conn);
+    result.map_err(GatewayError::daemon)
+}
 
-    read_daemon_response(&mut lines, 2)
-        .await
-        .map_err(GatewayError::daemon)
+/// Keeps one daemon connection open for the gateway's lifetime and feeds every
+/// event notification into the shared counters, reconnecting when it drops.
+async fn run_event_stats_reader(
+    config: Arc<GatewayConfig>,
+    request_ids: Arc<AtomicU64>,
+    stats: Arc<EventStats>,
+) {
+    loop {
+        if let Err(error) =
+            read_event_stats_once(config.as_ref(), request_ids.as_ref(), stats.as_ref()).await
+        {
+            eprintln!("event stats reader: {error}");
+        }
+        tokio::time::sleep(STATS_RECONNECT_DELAY).await;
+    }
+}
+
+async fn read_event_stats_once(
+    config: &GatewayConfig,
+    request_ids: &AtomicU64,
+    stats: &EventStats,
+) -> Result<(), String> {
+    let stream = connect_daemon_stream(config).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = DaemonLines::new(reader, config.max_response_bytes);
+    authenticate_daemon(config, request_ids, &mut writer, &mut lines).await?;
+
+    while let Some(line) = lines.next_line().await.map_err(|error| error.to_string())? {
+        let Ok(message) = serde_json::from_str::<Value>(line.trim()) else {
+            continue;
+        };
+        if !is_event_notification(&message) {
+            continue;
+        }
+        if let Some((workspace_id, kind)) = classify_event(&message) {
+            stats.record(&workspace_id, kind, unix_now_secs());
+        }
+    }
+    Err("daemon stream closed".to_string())
 }
 
 async fn console_index() -> Html<&'static str> {
@@ -474,27 +2127,81 @@ async fn api_root() -> Json<Value> {
         "endpoints": [
             "GET /health",
             "GET /api/workspaces",
-            "GET /api/drawings",
+            "GET /api/drawings?depth=<pages>",
+            "GET /api/stats?workspaceId=<id>",
+            "GET /api/audit?limit=<n>",
+            "GET /metrics",
             "GET /api/threads?workspaceId=<id>",
+            "GET /api/threads/all?limit=<n>&cursor=<cursor>",
+            "GET /api/workspaces/export?workspaceId=<id>",
             "POST /api/threads/start",
             "POST /api/threads/resume",
+            "POST /api/threads/interrupt",
+            "POST /api/threads/delete",
+            "POST /api/threads/import",
             "POST /api/threads/message",
             "POST /api/rpc",
-            "GET /ws/events"
+            "GET /ws/events",
+            "GET /sse/events"
         ]
     }))
 }
 
+async fn audit_log(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Value>, GatewayError> {
+    authorize_request(state.config.as_ref(), &headers, None)?;
+    let limit = query.limit.unwrap_or(100).min(AUDIT_LOG_CAPACITY);
+    Ok(Json(json!({
+        "entries": state.audit.recent(limit),
+        "capacity": AUDIT_LOG_CAPACITY,
+    })))
+}
+
 async fn health() -> Json<Value> {
     Json(json!({ "ok": true }))
 }
 
+async fn stats(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<Value>, GatewayError> {
+    authorize_request(state.config.as_ref(), &headers, None)?;
+
+    let workspace_id = query
+        .workspace_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    let workspaces = state.stats.snapshot(workspace_id, unix_now_secs());
+    Ok(Json(json!({
+        "bucketSeconds": STATS_BUCKET_SECS,
+        "bucketCount": STATS_BUCKET_COUNT,
+        "workspaces": workspaces,
+    })))
+}
+
+async fn metrics(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+) -> Result<Response, GatewayError> {
+    authorize_request(state.config.as_ref(), &headers, None)?;
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        render_metrics(&state.stats.totals()),
+    )
+        .into_response())
+}
+
 async fn list_workspaces(
     State(state): State<GatewayState>,
     headers: HeaderMap,
 ) -> Result<Json<Value>, GatewayError> {
     authorize_request(state.config.as_ref(), &headers, None)?;
-    let workspaces = call_daemon_rpc(state.config.as_ref(), "list_workspaces", json!({})).await?;
+    let workspaces = call_daemon_rpc(&state.daemon, "list_workspaces", json!({})).await?;
     Ok(Json(json!({ "workspaces": workspaces })))
 }
 
@@ -516,7 +2223,7 @@ async fn list_threads(
         "sortKey": query.sort_key,
     });
 
-    let raw = call_daemon_rpc(state.config.as_ref(), "list_threads", params).await?;
+    let raw = call_daemon_rpc(&state.daemon, "list_threads", params).await?;
     let (threads, next_cursor) = parse_thread_page(&raw);
 
     Ok(Json(ThreadListResponse {
@@ -530,32 +2237,54 @@ async fn list_threads(
 async fn list_drawings(
     State(state): State<GatewayState>,
     headers: HeaderMap,
+    Query(query): Query<DrawingsQuery>,
 ) -> Result<Json<DrawingsResponse>, GatewayError> {
     authorize_request(state.config.as_ref(), &headers, None)?;
 
-    let workspaces = call_daemon_rpc(state.config.as_ref(), "list_workspaces", json!({})).await?;
-    let mut snapshots = Vec::new();
+    let depth = query.depth.unwrap_or(1).clamp(1, MAX_DRAWINGS_DEPTH);
+    let workspaces = call_daemon_rpc(&state.daemon, "list_workspaces", json!({})).await?;
+    let daemon = state.daemon.as_ref();
+    // `buffered` keeps the workspace order while fetching several at once.
+    let snapshots = stream::iter(workspaces.as_array().cloned().unwrap_or_default())
+        .map(|workspace| fetch_drawing_snapshot(daemon, workspace, depth))
+        .buffered(DRAWINGS_FETCH_CONCURRENCY)
+        .collect()
+        .await;
 
-    for workspace in workspaces.as_array().into_iter().flatten() {
-        let mut snapshot = WorkspaceDrawingSnapshot {
-            workspace: workspace.clone(),
-            threads: Vec::new(),
-            next_cursor: None,
-            error: None,
-        };
+    Ok(Json(DrawingsResponse {
+        workspaces: snapshots,
+    }))
+}
 
-        let Some(workspace_id) = workspace.get("id").and_then(Value::as_str) else {
-            snapshot.error = Some("workspace is missing an `id` field".to_string());
-            snapshots.push(snapshot);
-            continue;
-        };
+/// Collects up to `depth` thread pages for one workspace. `next_cursor` is the
+/// first page left unfetched, including one whose fetch failed.
+async fn fetch_drawing_snapshot(
+    daemon: &DaemonPool,
+    workspace: Value,
+    depth: usize,
+) -> WorkspaceDrawingSnapshot {
+    let mut snapshot = WorkspaceDrawingSnapshot {
+        workspace,
+        threads: Vec::new(),
+        next_cursor: None,
+        error: None,
+    };
+
+    let Some(workspace_id) = snapshot.workspace.get("id").and_then(Value::as_str) else {
+        snapshot.error = Some("workspace is missing an `id` field".to_string());
+        return snapshot;
+    };
 
+    let workspace_id = workspace_id.to_string();
+    let mut cursor = None;
+    for _ in 0..depth {
         let thread_call = call_daemon_rpc(
-            state.config.as_ref(),
+            daemon,
             "list_threads",
             json!({
                 "workspaceId": workspace_id,
-                "limit": 20,
+                "cursor": cursor,
+                "limit": DRAWINGS_PAGE_SIZE,
                 "sortKey": "updated_at",
             }),
         )
@@ -564,44 +2293,508 @@ async fn list_drawings(
         match thread_call {
             Ok(raw) => {
                 let (threads, next_cursor) = parse_thread_page(&raw);
-                snapshot.threads = threads;
-                snapshot.next_cursor = next_cursor;
+                snapshot.threads.extend(threads);
+                cursor = next_cursor;
             }
             Err(error) => {
                 snapshot.error = Some(error.message);
+                break;
             }
         }
-
-        snapshots.push(snapshot);
+        if cursor.is_none() {
+            break;
+        }
     }
 
-    Ok(Json(DrawingsResponse {
-        workspaces: snapshots,
-    }))
+    snapshot.next_cursor = cursor;
+    snapshot
 }
 
-async fn start_thread(
+async fn list_all_threads(
     State(state): State<GatewayState>,
     headers: HeaderMap,
-    Json(request): Json<StartThreadRequest>,
-) -> Result<Json<Value>, GatewayError> {
+    Query(query): Query<ThreadFeedQuery>,
+) -> Result<Json<ThreadFeedResponse>, GatewayError> {
     authorize_request(state.config.as_ref(), &headers, None)?;
 
-    if request.workspace_id.trim().is_empty() {
-        return Err(GatewayError::bad_request("`workspaceId` must not be empty"));
-    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_FEED_LIMIT)
+        .clamp(1, MAX_FEED_LIMIT);
+    let cursor = match query.cursor.as_deref().filter(|raw| !raw.trim().is_empty()) {
+        Some(raw) => FeedCursor::decode(raw)?,
+        None => FeedCursor::default(),
+    };
 
-    let result = call_daemon_rpc(
-        state.config.as_ref(),
+    let workspaces = call_daemon_rpc(&state.daemon, "list_workspaces", json!({})).await?;
+    let mut positions = BTreeMap::new();
+    let mut sources = Vec::new();
+    for workspace in workspaces.as_array().into_iter().flatten() {
+        let Some(workspace_id) = workspace.get("id").and_then(Value::as_str) else {
+            continue;
+        };
+        let position = cursor
+            .positions
+            .get(workspace_id)
+            .cloned()
+            .unwrap_or_default();
+        if position.done {
+            positions.insert(workspace_id.to_string(), position);
+            continue;
+        }
+        sources.push(FeedSource {
+            workspace_id: workspace_id.to_string(),
+            workspace_name: workspace.get("name").cloned().unwrap_or(Value::Null),
+            workspace_path: normalize_root_path(
+                workspace
+                    .get("path")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default(),
+            ),
+            page_cursor: position.cursor,
+            next_cursor: None,
+            buffer: VecDeque::new(),
+            loaded: false,
+            failed: false,
+            empty_pages: 0,
+        });
+    }
+
+    let mut warnings = Vec::new();
+    let mut threads = Vec::new();
+    let mut last_key = cursor.after.clone();
+    while threads.len() < limit {
+        fetch_feed_pages(
+            &state.daemon,
+            &mut sources,
+            cursor.after.as_ref(),
+            limit,
+            &mut warnings,
+        )
+        .await;
+        // A stalled source may still hold a thread that sorts before every
+        // buffered one, so the page ends early and resumes from its cursor.
+        if sources.iter().any(FeedSource::stalled) {
+            break;
+        }
+        let Some(source) = sources
+            .iter_mut()
+            .filter(|source| !source.buffer.is_empty())
+            .min_by(|left, right| left.buffer[0].0.cmp(&right.buffer[0].0))
+        else {
+            break;
+        };
+        let Some((key, thread)) = source.buffer.pop_front() else {
+            break;
+        };
+        threads.push(json!({
+            "workspaceId": source.workspace_id,
+            "workspaceName": source.workspace_name,
+            "thread": thread,
+        }));
+        last_key = Some(key);
+    }
+
+    for source in &sources {
+        positions.insert(source.workspace_id.clone(), source.position());
+    }
+    let next_cursor = positions.values().any(|position| !position.done).then(|| {
+        FeedCursor {
+            after: last_key,
+            positions,
+        }
+        .encode()
+    });
+
+    Ok(Json(ThreadFeedResponse {
+        threads,
+        next_cursor,
+        warnings,
+    }))
+}
+
+/// Refills every drained feed source from the daemon, a few workspaces at a
+/// time. Threads from other workspaces, or at or before `after` (returned by
+/// an earlier page), are dropped, which can leave a buffer empty and trigger
+/// another round. A source stops when its cursor repeats, and pauses for this
+/// request after `MAX_FEED_EMPTY_PAGES` pages with nothing to buffer.
+async fn fetch_feed_pages(
+    daemon: &DaemonPool,
+    sources: &mut [FeedSource],
+    after: Option<&FeedKey>,
+    page_size: usize,
+    warnings: &mut Vec<FeedWarning>,
+) {
+    loop {
+        let pending: Vec<(usize, String, Option<String>)> = sources
+            .iter()
+            .enumerate()
+            .filter(|(_, source)| source.needs_fetch())
+            .map(|(index, source)| {
+                let cursor = if source.loaded {
+                    source.next_cursor.clone()
+                } else {
+                    source.page_cursor.clone()
+                };
+                (index, source.workspace_id.clone(), cursor)
+            })
+            .collect();
+        if pending.is_empty() {
+            return;
+        }
+
+        let results: Vec<_> = stream::iter(pending)
+            .map(|(index, workspace_id, cursor)| async move {
+                let result = call_daemon_rpc(
+                    daemon,
+                    "list_threads",
+                    json!({
+                        "workspaceId": workspace_id,
+                        "cursor": cursor,
+                        "limit": page_size,
+                        "sortKey": "updated_at",
+                    }),
+                )
+                .await;
+                (index, cursor, result)
+            })
+            .buffer_unordered(FEED_FETCH_CONCURRENCY)
+            .collect()
+            .await;
+
+        for (index, cursor, result) in results {
+            let source = &mut sources[index];
+            match result {
+                Ok(raw) => {
+                    let (page, next_cursor) = parse_thread_page(&raw);
+                    source.next_cursor = next_cursor.filter(|next| cursor.as_ref() != Some(next));
+                    source.page_cursor = cursor;
+                    source.loaded = true;
+                    source.buffer = page
+                        .into_iter()
+                        .filter(|thread| thread_in_workspace(thread, &source.workspace_path))
+                        .filter_map(|thread| {
+                            let key = FeedKey {
+                                updated_at: thread_updated_at(&thread),
+                                workspace_id: source.workspace_id.clone(),
+                                thread_id: thread.get("id")?.as_str()?.to_string(),
+                            };
+                            after
+                                .is_none_or(|after| key > *after)
+                                .then_some((key, thread))
+                        })
+                        .collect();
+                    source
+                        .buffer
+                        .make_contiguous()
+                        .sort_by(|left, right| left.0.cmp(&right.0));
+                    if source.buffer.is_empty() {
+                        source.empty_pages += 1;
+                    }
+                }
+                Err(error) => {
+                    source.failed = true;
+                    source.buffer.clear();
+                    warnings.push(FeedWarning {
+                        workspace_id: source.workspace_id.clone(),
+                        error: error.message,
+                    });
+                }
+            }
+        }
+    }
+}
+
+async fn export_workspace(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(query): Query<ExportWorkspaceQuery>,
+) -> Result<Response, GatewayError> {
+    authorize_request(state.config.as_ref(), &headers, None)?;
+
+    if query.workspace_id.trim().is_empty() {
+        return Err(GatewayError::bad_request("`workspaceId` must not be empty"));
+    }
+    let max_threads = query
+        .max_threads
+        .unwrap_or(DEFAULT_EXPORT_MAX_THREADS)
+        .clamp(1, MAX_EXPORT_MAX_THREADS);
+    let Ok(permit) = state.exports.clone().try_acquire_owned() else {
+        let mut response =
+            GatewayError::too_many_requests("too many workspace exports are already running")
+                .into_response();
+        response.headers_mut().insert(
+            header::RETRY_AFTER,
+            HeaderValue::from(EXPORT_RETRY_AFTER_SECS),
+        );
+        return Ok(response);
+    };
+
+    let workspaces = call_daemon_rpc(&state.daemon, "list_workspaces", json!({})).await?;
+    let workspace = workspaces
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|workspace| {
+            workspace.get("id").and_then(Value::as_str) == Some(query.workspace_id.as_str())
+        })
+        .cloned()
+        .ok_or_else(|| GatewayError::not_found("workspace not found"))?;
+    let workspace_path = normalize_root_path(
+        workspace
+            .get("path")
+            .and_then(Value::as_str)
+            .unwrap_or_default(),
+    );
+
+    let mut threads = Vec::new();
+    let mut cursor: Option<String> = None;
+    let truncated = loop {
+        let raw = call_daemon_rpc(
+            &state.daemon,
+            "list_threads",
+            json!({
+                "workspaceId": query.workspace_id,
+                "cursor": cursor,
+                "limit": EXPORT_PAGE_SIZE,
+                "sortKey": "updated_at",
+            }),
+        )
+        .await?;
+        let (page, next_cursor) = parse_thread_page(&raw);
+        threads.extend(
+            page.into_iter()
+                .filter(|thread| thread_in_workspace(thread, &workspace_path)),
+        );
+        if threads.len() >= max_threads {
+            let more = threads.len() > max_threads || next_cursor.is_some();
+            threads.truncate(max_threads);
+            break more;
+        }
+        match next_cursor {
+            Some(next) if cursor.as_deref() != Some(next.as_str()) => cursor = Some(next),
+            _ => break false,
+        }
+    };
+
+    let daemon_version = call_daemon_rpc(&state.daemon, "ping", Value::Null)
+        .await
+        .ok()
+        .and_then(|value| {
+            value
+                .get("version")
+                .and_then(Value::as_str)
+                .map(ToString::to_string)
+        });
+
+    let manifest = ExportManifest {
+        workspace,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        gateway_version: env!("CARGO_PKG_VERSION"),
+        daemon_version,
+        thread_count: threads.len(),
+        exported_count: 0,
+        truncated,
+        failures: Vec::new(),
+    };
+
+    let (tx, mut rx) = mpsc::channel::<ExportChunk>(4);
+    let daemon = state.daemon.clone();
+    let workspace_id = query.workspace_id.clone();
+    let thread_limit = state.config.max_export_thread_bytes;
+    tokio::spawn(async move {
+        let _permit = permit;
+        let archive = ExportArchive::new(tx.clone());
+        if let Err(error) = write_workspace_export(
+            daemon,
+            workspace_id,
+            thread_limit,
+            threads,
+            manifest,
+            archive,
+        )
+        .await
+        {
+            let _ = tx.send(Err(std::io::Error::other(error))).await;
+        }
+    });
+
+    let stream = futures_util::stream::poll_fn(move |cx| rx.poll_recv(cx));
+    let filename = format!(
+        "attachment; filename=\"codex-workspace-{}.zip\"",
+        sanitize_file_component(&query.workspace_id)
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, filename),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+/// Resumes threads with bounded concurrency and writes them into the archive in
+/// listing order, one rendered thread at a time. Threads larger than
+/// `thread_limit` are recorded as failures rather than read whole.
+async fn write_workspace_export(
+    daemon: Arc<DaemonPool>,
+    workspace_id: String,
+    thread_limit: usize,
+    threads: Vec<Value>,
+    mut manifest: ExportManifest,
+    mut archive: ExportArchive,
+) -> Result<(), String> {
+    let mut pending = VecDeque::new();
+    let mut queued = threads.into_iter().enumerate();
+
+    loop {
+        while pending.len() < EXPORT_RESUME_CONCURRENCY {
+            let Some((index, summary)) = queued.next() else {
+                break;
+            };
+            let thread_id = summary
+                .get("id")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let daemon = daemon.clone();
+            let params = json!({ "workspaceId": workspace_id, "threadId": thread_id });
+            let task = tokio::spawn(async move {
+                call_daemon_rpc_with_limit(&daemon, "resume_thread", params, thread_limit).await
+            });
+            pending.push_back((index, thread_id, task));
+        }
+
+        let Some((index, thread_id, task)) = pending.pop_front() else {
+            break;
+        };
+        let outcome = match task.await {
+            Ok(outcome) => outcome.map_err(|error| {
+                if error.status == StatusCode::PAYLOAD_TOO_LARGE {
+                    format!(
+                        "thread exceeds the {thread_limit} byte export limit \
+                         (--max-export-thread-bytes)"
+                    )
+                } else {
+                    error.message
+                }
+            }),
+            Err(error) => Err(format!("resume task failed: {error}")),
+        };
+        let raw = match outcome {
+            Ok(raw) => raw,
+            Err(error) => {
+                manifest.failures.push(ExportFailure { thread_id, error });
+                continue;
+            }
+        };
+
+        let response = peel_result_envelope(peel_result_envelope(&raw));
+        let thread = response.get("thread").unwrap_or(response);
+        let stem = export_file_stem(index, &thread_id);
+        archive
+            .add_file(
+                &format!("{stem}.md"),
+                render_thread_markdown(thread).as_bytes(),
+            )
+            .await?;
+        let json = serde_json::to_vec_pretty(&raw).map_err(|error| error.to_string())?;
+        archive.add_file(&format!("{stem}.json"), &json).await?;
+        manifest.exported_count += 1;
+    }
+
+    let manifest = serde_json::to_vec_pretty(&manifest).map_err(|error| error.to_string())?;
+    archive.add_file("manifest.json", &manifest).await?;
+    archive.finish().await
+}
+
+async fn start_thread(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<StartThreadRequest>,
+) -> Result<Response, GatewayError> {
+    authorize_request(state.config.as_ref(), &headers, None)?;
+
+    if request.workspace_id.trim().is_empty() {
+        return Err(GatewayError::bad_request("`workspaceId` must not be empty"));
+    }
+    let idempotency_key = parse_idempotency_key(&headers)?;
+
+    let daemon = state.daemon.clone();
+    run_idempotent(
+        &state,
+        &headers,
+        "/api/threads/start",
+        idempotency_key,
+        async move {
+            let result = call_daemon_rpc(
+                daemon.as_ref(),
+                "start_thread",
+                json!({ "workspaceId": request.workspace_id }),
+            )
+            .await?;
+
+            let thread_id = parse_thread_id_from_start_response(&result);
+
+            Ok(json!({
+                "threadId": thread_id,
+                "result": result,
+            }))
+        },
+    )
+    .await
+}
+
+async fn import_thread(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<ImportThreadRequest>,
+) -> Result<Json<Value>, GatewayError> {
+    authorize_request(state.config.as_ref(), &headers, None)?;
+
+    if request.workspace_id.trim().is_empty() {
+        return Err(GatewayError::bad_request("`workspaceId` must not be empty"));
+    }
+    let (messages, title) = import_transcript(&request)?;
+
+    let daemon = state.daemon.as_ref();
+    let started = call_daemon_rpc(
+        daemon,
         "start_thread",
         json!({ "workspaceId": request.workspace_id }),
     )
     .await?;
+    let thread_id = parse_thread_id_from_start_response(&started)
+        .ok_or_else(|| GatewayError::daemon("daemon did not return a thread id"))?;
+
+    if let Some(title) = title.as_deref() {
+        call_daemon_rpc(
+            daemon,
+            "set_thread_name",
+            json!({
+                "workspaceId": request.workspace_id,
+                "threadId": thread_id,
+                "name": title,
+            }),
+        )
+        .await?;
+    }
 
-    let thread_id = parse_thread_id_from_start_response(&result);
+    let result = call_daemon_rpc(
+        daemon,
+        "send_user_message",
+        json!({
+            "workspaceId": request.workspace_id,
+            "threadId": thread_id,
+            "text": render_import_seed(title.as_deref(), &messages),
+        }),
+    )
+    .await?;
 
     Ok(Json(json!({
         "threadId": thread_id,
+        "importedMessages": messages.len(),
         "result": result,
     })))
 }
@@ -621,7 +2814,7 @@ async fn resume_thread(
     }
 
     let result = call_daemon_rpc(
-        state.config.as_ref(),
+        &state.daemon,
         "resume_thread",
         json!({
             "workspaceId": request.workspace_id,
@@ -633,10 +2826,10 @@ async fn resume_thread(
     Ok(Json(RpcResponse { result }))
 }
 
-async fn send_message(
+async fn interrupt_thread(
     State(state): State<GatewayState>,
     headers: HeaderMap,
-    Json(request): Json<SendMessageRequest>,
+    Json(request): Json<InterruptThreadRequest>,
 ) -> Result<Json<RpcResponse>, GatewayError> {
     authorize_request(state.config.as_ref(), &headers, None)?;
 
@@ -646,22 +2839,49 @@ async fn send_message(
     if request.thread_id.trim().is_empty() {
         return Err(GatewayError::bad_request("`threadId` must not be empty"));
     }
-    if request.text.trim().is_empty() {
-        return Err(GatewayError::bad_request("`text` must not be empty"));
+    let turn_id = request
+        .turn_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|turn_id| !turn_id.is_empty())
+        .ok_or_else(|| GatewayError::bad_request("`turnId` must not be empty"))?;
+
+    let result = call_daemon_rpc(
+        &state.daemon,
+        "turn_interrupt",
+        json!({
+            "workspaceId": request.workspace_id,
+            "threadId": request.thread_id,
+            "turnId": turn_id,
+        }),
+    )
+    .await?;
+
+    Ok(Json(RpcResponse { result }))
+}
+
+/// The daemon has no hard delete; like removing a thread in the desktop app,
+/// this archives it so it drops out of thread lists.
+async fn delete_thread(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<DeleteThreadRequest>,
+) -> Result<Json<RpcResponse>, GatewayError> {
+    authorize_request(state.config.as_ref(), &headers, None)?;
+
+    if request.workspace_id.trim().is_empty() {
+        return Err(GatewayError::bad_request("`workspaceId` must not be empty"));
+    }
+    if request.thread_id.trim().is_empty() {
+        return Err(GatewayError::bad_request("`threadId` must not be empty"));
     }
 
     let result = call_daemon_rpc(
-        state.config.as_ref(),
-        "send_user_message",
+        &state.daemon,
+        "archive_thread",
         json!({
             "workspaceId": request.workspace_id,
             "threadId": request.thread_id,
-            "text": request.text,
-            "model": request.model,
-            "effort": request.effort,
-            "accessMode": request.access_mode,
-            "images": request.images,
-            "collaborationMode": request.collaboration_mode,
         }),
     )
     .await?;
@@ -669,6 +2889,53 @@ async fn send_message(
     Ok(Json(RpcResponse { result }))
 }
 
+async fn send_message(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<SendMessageRequest>,
+) -> Result<Response, GatewayError> {
+    authorize_request(state.config.as_ref(), &headers, None)?;
+
+    if request.workspace_id.trim().is_empty() {
+        return Err(GatewayError::bad_request("`workspaceId` must not be empty"));
+    }
+    if request.thread_id.trim().is_empty() {
+        return Err(GatewayError::bad_request("`threadId` must not be empty"));
+    }
+    if request.text.trim().is_empty() {
+        return Err(GatewayError::bad_request("`text` must not be empty"));
+    }
+    let idempotency_key = parse_idempotency_key(&headers)?;
+
+    let daemon = state.daemon.clone();
+    run_idempotent(
+        &state,
+        &headers,
+        "/api/threads/message",
+        idempotency_key,
+        async move {
+            let result = call_daemon_rpc(
+                daemon.as_ref(),
+                "send_user_message",
+                json!({
+                    "workspaceId": request.workspace_id,
+                    "threadId": request.thread_id,
+                    "text": request.text,
+                    "model": request.model,
+                    "effort": request.effort,
+                    "accessMode": request.access_mode,
+                    "images": request.images,
+                    "collaborationMode": request.collaboration_mode,
+                }),
+            )
+            .await?;
+
+            Ok(json!(RpcResponse { result }))
+        },
+    )
+    .await
+}
+
 async fn rpc_proxy(
     State(state): State<GatewayState>,
     headers: HeaderMap,
@@ -680,7 +2947,7 @@ async fn rpc_proxy(
         return Err(GatewayError::bad_request("`method` must not be empty"));
     }
 
-    let result = call_daemon_rpc(state.config.as_ref(), &request.method, request.params).await?;
+    let result = call_daemon_rpc(&state.daemon, &request.method, request.params).await?;
     Ok(Json(RpcResponse { result }))
 }
 
@@ -689,9 +2956,57 @@ async fn ws_events(
     State(state): State<GatewayState>,
     headers: HeaderMap,
     Query(query): Query<WsTokenQuery>,
+) -> Result<Response, GatewayError> {
+    let fallback_token = extract_ws_protocol_token(&headers).or(query.token.as_deref());
+    authorize_request(state.config.as_ref(), &headers, fallback_token)?;
+    Ok(ws
+        .protocols([WS_PROTOCOL])
+        .on_upgrade(move |socket| handle_ws_connection(socket, state)))
+}
+
+/// Server-sent events fallback for clients whose proxies strip WebSocket
+/// upgrades. Streams the same notifications as `/ws/events`, without RPC or
+/// reconnects: the stream ends with `event: disconnected` when the daemon drops.
+async fn sse_events(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(query): Query<WsTokenQuery>,
 ) -> Result<Response, GatewayError> {
     authorize_request(state.config.as_ref(), &headers, query.token.as_deref())?;
-    Ok(ws.on_upgrade(move |socket| handle_ws_connection(socket, state)))
+    let link = open_daemon_event_stream(&state)
+        .await
+        .map_err(GatewayError::daemon)?;
+    Ok(Sse::new(daemon_event_sse_stream(link))
+        .keep_alive(
+            KeepAlive::new()
+                .interval(SSE_KEEP_ALIVE_INTERVAL)
+                .text("keep-alive"),
+        )
+        .into_response())
+}
+
+fn daemon_event_sse_stream(link: WsDaemonLink) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(Some(link), |link| async move {
+        let mut link = link?;
+        loop {
+            let reason = match link.lines.next_line().await {
+                Ok(Some(line)) => {
+                    let trimmed = line.trim();
+                    let Ok(message) = serde_json::from_str::<Value>(trimmed) else {
+                        continue;
+                    };
+                    if is_event_notification(&message) {
+                        return Some((Ok(Event::default().data(trimmed)), Some(link)));
+                    }
+                    continue;
+                }
+                Ok(None) => "daemon stream closed",
+                Err(_) => "daemon read failed",
+            };
+            let event = Event::default().event("disconnected").data(reason);
+            return Some((Ok(event), None));
+        }
+    })
 }
 
 async fn send_ws_json(socket: &mut WebSocket, payload: Value) -> Result<(), ()> {
@@ -701,81 +3016,199 @@ async fn send_ws_json(socket: &mut WebSocket, payload: Value) -> Result<(), ()>
         .map_err(|_| ())
 }
 
-async fn handle_ws_connection(mut socket: WebSocket, state: GatewayState) {
-    let stream = match connect_daemon_stream(state.config.as_ref()).await {
-        Ok(stream) => stream,
-        Err(error) => {
-            let _ = send_ws_json(
-                &mut socket,
-                json!({
-                    "type": "gateway/error",
-                    "message": error,
-                }),
-            )
-            .await;
-            let _ = socket.send(Message::Close(None)).await;
-            return;
-        }
-    };
+/// The daemon side of one event stream client. Events arrive on `lines`;
+/// `/ws/events` RPCs go out on `writer`, keyed in `pending` by daemon request
+/// id. `/sse/events` only keeps `writer` so the connection stays open.
+struct WsDaemonLink {
+    writer: OwnedWriteHalf,
+    lines: DaemonLines,
+    pending: HashMap<u64, PendingWsRpc>,
+}
+
+struct PendingWsRpc {
+    client_id: Value,
+    method: String,
+    sent_at: Instant,
+}
 
+/// Connects, authenticates, and pings a daemon connection for forwarding
+/// events and browser RPCs.
+async fn open_daemon_event_stream(state: &GatewayState) -> Result<WsDaemonLink, String> {
+    let stream = connect_daemon_stream(state.config.as_ref()).await?;
     let (reader, mut writer) = stream.into_split();
-    let mut lines = BufReader::new(reader).lines();
+    let mut lines = DaemonLines::new(reader, state.config.max_response_bytes);
 
-    if let Err(error) = authenticate_daemon(state.config.as_ref(), &mut writer, &mut lines).await {
-        let _ = send_ws_json(
-            &mut socket,
-            json!({
-                "type": "gateway/error",
-                "message": error,
-            }),
-        )
-        .await;
-        let _ = socket.send(Message::Close(None)).await;
-        return;
-    }
+    authenticate_daemon(
+        state.config.as_ref(),
+        state.request_ids.as_ref(),
+        &mut writer,
+        &mut lines,
+    )
+    .await?;
+    let ping_id = next_request_id(&state.request_ids);
+    send_daemon_request(&mut writer, ping_id, "ping", Value::Null).await?;
+    read_daemon_response(&mut lines, ping_id).await?;
+    Ok(WsDaemonLink {
+        writer,
+        lines,
+        pending: HashMap::new(),
+    })
+}
 
-    if let Err(error) = send_daemon_request(&mut writer, 2, "ping", Value::Null).await {
-        let _ = send_ws_json(
-            &mut socket,
-            json!({
-                "type": "gateway/error",
-                "message": error,
-            }),
+enum WsStreamEnd {
+    ClientClosed,
+    DaemonLost(String),
+}
+
+fn ws_rpc_error(id: Value, message: impl Into<String>) -> Value {
+    json!({
+        "type": "rpc/error",
+        "id": id,
+        "error": message.into(),
+    })
+}
+
+/// Answers browser frames. `link` is `None` while the daemon is reconnecting,
+/// in which case RPC frames are refused. Returns `false` once the browser
+/// socket is gone.
+async fn handle_ws_client_frame(
+    socket: &mut WebSocket,
+    link: Option<&mut WsDaemonLink>,
+    request_ids: &AtomicU64,
+    incoming: Option<Result<Message, axum::Error>>,
+) -> bool {
+    let payload = match incoming {
+        Some(Ok(Message::Close(_))) | None | Some(Err(_)) => return false,
+        Some(Ok(Message::Ping(payload))) => {
+            return socket.send(Message::Pong(payload)).await.is_ok();
+        }
+        Some(Ok(Message::Text(payload))) => payload,
+        Some(Ok(_)) => return true,
+    };
+    if payload.trim().eq_ignore_ascii_case("ping") {
+        return send_ws_json(socket, json!({ "type": "gateway/pong" }))
+            .await
+            .is_ok();
+    }
+    match serde_json::from_str::<Value>(&payload) {
+        Ok(frame) if frame.is_object() => forward_ws_rpc(socket, link, request_ids, frame).await,
+        _ => send_ws_json(
+            socket,
+            ws_rpc_error(Value::Null, "RPC frames must be JSON objects"),
         )
-        .await;
-        let _ = socket.send(Message::Close(None)).await;
-        return;
+        .await
+        .is_ok(),
     }
+}
 
-    if let Err(error) = read_daemon_response(&mut lines, 2).await {
-        let _ = send_ws_json(
-            &mut socket,
-            json!({
-                "type": "gateway/error",
-                "message": error,
-            }),
-        )
-        .await;
-        let _ = socket.send(Message::Close(None)).await;
-        return;
+/// Sends a browser `{ id, method, params }` frame to the daemon under a fresh
+/// daemon-side id. The reply is relayed by `forward_daemon_events`.
+async fn forward_ws_rpc(
+    socket: &mut WebSocket,
+    link: Option<&mut WsDaemonLink>,
+    request_ids: &AtomicU64,
+    frame: Value,
+) -> bool {
+    let client_id = frame.get("id").cloned().unwrap_or(Value::Null);
+    let method = frame
+        .get("method")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .unwrap_or_default();
+    let error = if method.is_empty() {
+        "`method` is required".to_string()
+    } else if let Some(link) = link {
+        if link.pending.len() >= MAX_WS_PENDING_RPCS {
+            format!("too many RPCs in flight (max {MAX_WS_PENDING_RPCS})")
+        } else {
+            let id = next_request_id(request_ids);
+            let params = frame.get("params").cloned().unwrap_or(Value::Null);
+            match send_daemon_request(&mut link.writer, id, method, params).await {
+                Ok(()) => {
+                    link.pending.insert(
+                        id,
+                        PendingWsRpc {
+                            client_id,
+                            method: method.to_string(),
+                            sent_at: Instant::now(),
+                        },
+                    );
+                    return true;
+                }
+                Err(error) => error,
+            }
+        }
+    } else {
+        "daemon is reconnecting".to_string()
+    };
+    send_ws_json(socket, ws_rpc_error(client_id, error))
+        .await
+        .is_ok()
+}
+
+/// Fails every RPC still waiting on a daemon connection that was lost.
+async fn fail_pending_ws_rpcs(socket: &mut WebSocket, link: WsDaemonLink, reason: &str) -> bool {
+    for (_, rpc) in link.pending {
+        if send_ws_json(socket, ws_rpc_error(rpc.client_id, reason))
+            .await
+            .is_err()
+        {
+            return false;
+        }
     }
+    true
+}
 
-    if send_ws_json(
-        &mut socket,
-        json!({
-            "type": "gateway/ready",
-            "daemon": state.config.daemon_addr,
-        }),
-    )
-    .await
-    .is_err()
-    {
-        return;
+/// Answers RPCs the daemon has not replied to within `timeout`. A reply that
+/// arrives later no longer matches a pending entry and is dropped.
+async fn expire_ws_rpcs(
+    socket: &mut WebSocket,
+    link: &mut WsDaemonLink,
+    timeout: Duration,
+) -> bool {
+    let now = Instant::now();
+    let expired: Vec<u64> = link
+        .pending
+        .iter()
+        .filter(|(_, rpc)| now.duration_since(rpc.sent_at) >= timeout)
+        .map(|(id, _)| *id)
+        .collect();
+    for id in expired {
+        let Some(rpc) = link.pending.remove(&id) else {
+            continue;
+        };
+        let message = format!(
+            "daemon did not answer `{}` within {}s",
+            rpc.method,
+            timeout.as_secs_f32()
+        );
+        if send_ws_json(socket, ws_rpc_error(rpc.client_id, message))
+            .await
+            .is_err()
+        {
+            return false;
+        }
+    }
+    true
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
     }
+}
 
+async fn forward_daemon_events(
+    socket: &mut WebSocket,
+    link: &mut WsDaemonLink,
+    state: &GatewayState,
+) -> WsStreamEnd {
+    let timeout = state.config.daemon_timeout;
     loop {
+        let next_expiry = link.pending.values().map(|rpc| rpc.sent_at + timeout).min();
         tokio::select! {
-            next_line = lines.next_line() => {
+            next_line = link.lines.next_line() => {
                 match next_line {
                     Ok(Some(line)) => {
                         let trimmed = line.trim();
@@ -786,57 +3219,147 @@ async fn handle_ws_connection(mut socket: WebSocket, state: GatewayState) {
                             Ok(value) => value,
                             Err(_) => continue,
                         };
-                        if !is_event_notification(&message) {
-                            continue;
-                        }
-                        if socket.send(Message::Text(trimmed.to_string().into())).await.is_err() {
-                            break;
+                        let sent = if is_event_notification(&message) {
+                            socket.send(Message::Text(trimmed.to_string().into())).await.is_ok()
+                        } else if let Some(PendingWsRpc { client_id, .. }) = message
+                            .get("id")
+                            .and_then(Value::as_u64)
+                            .and_then(|id| link.pending.remove(&id))
+                        {
+                            let frame = match daemon_result(&message) {
+                                Ok(result) => json!({
+                                    "type": "rpc/response",
+                                    "id": client_id,
+                                    "result": result,
+                                }),
+                                Err(error) => ws_rpc_error(client_id, error),
+                            };
+                            send_ws_json(socket, frame).await.is_ok()
+                        } else {
+                            true
+                        };
+                        if !sent {
+                            return WsStreamEnd::ClientClosed;
                         }
                     }
-                    Ok(None) => {
-                        let _ = send_ws_json(
-                            &mut socket,
-                            json!({
-                                "type": "gateway/disconnected",
-                                "message": "daemon stream closed",
-                            }),
-                        )
-                        .await;
-                        break;
-                    }
-                    Err(_) => {
-                        let _ = send_ws_json(
-                            &mut socket,
-                            json!({
-                                "type": "gateway/disconnected",
-                                "message": "daemon read failed",
-                            }),
-                        )
-                        .await;
-                        break;
-                    }
+                    Ok(None) => return WsStreamEnd::DaemonLost("daemon stream closed".to_string()),
+                    Err(error) => return WsStreamEnd::DaemonLost(error.to_string()),
                 }
             }
             incoming = socket.recv() => {
-                match incoming {
-                    Some(Ok(Message::Close(_))) | None => break,
-                    Some(Ok(Message::Ping(payload))) => {
-                        if socket.send(Message::Pong(payload)).await.is_err() {
-                            break;
-                        }
-                    }
-                    Some(Ok(Message::Text(payload))) => {
-                        if payload.trim().eq_ignore_ascii_case("ping") {
-                            if send_ws_json(&mut socket, json!({ "type": "gateway/pong" })).await.is_err() {
-                                break;
-                            }
-                        }
+                if !handle_ws_client_frame(socket, Some(link), &state.request_ids, incoming).await {
+                    return WsStreamEnd::ClientClosed;
+                }
+            }
+            _ = sleep_until(next_expiry) => {
+                if !expire_ws_rpcs(socket, link, timeout).await {
+                    return WsStreamEnd::ClientClosed;
+                }
+            }
+        }
+    }
+}
+
+/// Re-establishes the daemon stream with exponential backoff while the browser
+/// socket stays open. Returns `None` when the browser leaves or the configured
+/// attempts run out, after telling the browser why.
+async fn reconnect_daemon_event_stream(
+    socket: &mut WebSocket,
+    state: &GatewayState,
+    mut reason: String,
+) -> Option<WsDaemonLink> {
+    let max_attempts = state.config.ws_reconnect_attempts;
+    let mut delay = WS_RECONNECT_INITIAL_DELAY;
+    for attempt in 1..=max_attempts {
+        send_ws_json(
+            socket,
+            json!({
+                "type": "gateway/reconnecting",
+                "attempt": attempt,
+                "maxAttempts": max_attempts,
+                "delayMs": delay.as_millis() as u64,
+                "message": reason,
+            }),
+        )
+        .await
+        .ok()?;
+
+        let sleep = tokio::time::sleep(delay);
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                _ = &mut sleep => break,
+                incoming = socket.recv() => {
+                    if !handle_ws_client_frame(socket, None, &state.request_ids, incoming).await {
+                        return None;
                     }
-                    Some(Ok(_)) => {}
-                    Some(Err(_)) => break,
                 }
             }
         }
+
+        match open_daemon_event_stream(state).await {
+            Ok(stream) => return Some(stream),
+            Err(error) => reason = error,
+        }
+        delay = (delay * 2).min(WS_RECONNECT_MAX_DELAY);
+    }
+
+    let _ = send_ws_json(
+        socket,
+        json!({
+            "type": "gateway/disconnected",
+            "message": reason,
+        }),
+    )
+    .await;
+    None
+}
+
+async fn handle_ws_connection(mut socket: WebSocket, state: GatewayState) {
+    let mut link = match open_daemon_event_stream(&state).await {
+        Ok(stream) => stream,
+        Err(error) => {
+            let _ = send_ws_json(
+                &mut socket,
+                json!({
+                    "type": "gateway/error",
+                    "message": error,
+                }),
+            )
+            .await;
+            let _ = socket.send(Message::Close(None)).await;
+            return;
+        }
+    };
+
+    let mut reconnected = false;
+    loop {
+        if send_ws_json(
+            &mut socket,
+            json!({
+                "type": "gateway/ready",
+                "daemon": state.config.daemon_addr,
+                "reconnected": reconnected,
+            }),
+        )
+        .await
+        .is_err()
+        {
+            return;
+        }
+
+        let reason = match forward_daemon_events(&mut socket, &mut link, &state).await {
+            WsStreamEnd::ClientClosed => break,
+            WsStreamEnd::DaemonLost(reason) => reason,
+        };
+        if !fail_pending_ws_rpcs(&mut socket, link, &reason).await {
+            return;
+        }
+        match reconnect_daemon_event_stream(&mut socket, &state, reason).await {
+            Some(next) => link = next,
+            None => break,
+        }
+        reconnected = true;
     }
 
     let _ = socket.send(Message::Close(None)).await;
@@ -846,7 +3369,8 @@ fn build_router(state: GatewayState) -> Router {
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_headers(Any)
-        .allow_methods([Method::GET, Method::POST, Method::OPTIONS]);
+        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .expose_headers([HeaderName::from_static(IDEMPOTENT_REPLAY_HEADER)]);
 
     Router::new()
         .route("/", get(console_index))
@@ -855,15 +3379,25 @@ fn build_router(state: GatewayState) -> Router {
         .route("/console/app.js", get(console_js))
         .route("/console/styles.css", get(console_css))
         .route("/health", get(health))
+        .route("/metrics", get(metrics))
         .route("/api", get(api_root))
+        .route("/api/stats", get(stats))
+        .route("/api/audit", get(audit_log))
         .route("/api/workspaces", get(list_workspaces))
+        .route("/api/workspaces/export", get(export_workspace))
         .route("/api/drawings", get(list_drawings))
         .route("/api/threads", get(list_threads))
+        .route("/api/threads/all", get(list_all_threads))
         .route("/api/threads/start", post(start_thread))
         .route("/api/threads/resume", post(resume_thread))
+        .route("/api/threads/interrupt", post(interrupt_thread))
+        .route("/api/threads/delete", post(delete_thread))
+        .route("/api/threads/import", post(import_thread))
         .route("/api/threads/message", post(send_message))
         .route("/api/rpc", post(rpc_proxy))
         .route("/ws/events", get(ws_events))
+        .route("/sse/events", get(sse_events))
+        .layer(middleware::from_fn_with_state(state.clone(), audit_auth))
         .with_state(state)
         .layer(cors)
 }
@@ -891,9 +3425,25 @@ fn main() {
         let listen_addr = config.listen;
         let daemon_addr = config.daemon_addr.clone();
         let auth_enabled = config.api_token.is_some();
+        let config = Arc::new(config);
+        let request_ids = Arc::new(AtomicU64::new(1));
         let state = GatewayState {
-            config: Arc::new(config),
+            daemon: Arc::new(DaemonPool::new(config.clone(), request_ids.clone())),
+            request_ids,
+            config,
+            idempotency: Arc::new(IdempotencyCache::new(
+                IDEMPOTENCY_TTL,
+                IDEMPOTENCY_MAX_ENTRIES,
+            )),
+            stats: Arc::new(EventStats::default()),
+            audit: Arc::new(AuthAudit::new()),
+            exports: Arc::new(Semaphore::new(MAX_CONCURRENT_EXPORTS)),
         };
+        tokio::spawn(run_event_stats_reader(
+            state.config.clone(),
+            state.request_ids.clone(),
+            state.stats.clone(),
+        ));
 
         let app = build_router(state);
 
@@ -908,17 +3458,197 @@ fn main() {
             if auth_enabled { "enabled" } else { "disabled" }
         );
 
-        axum::serve(listener, app)
-            .await
-            .unwrap_or_else(|error| panic!("web gateway server failed: {error}"));
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap_or_else(|error| panic!("web gateway server failed: {error}"));
     });
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{extract_request_token, is_event_notification};
-    use axum::http::{header, HeaderMap, HeaderValue};
-    use serde_json::json;
+    use super::{
+        build_router, call_daemon_rpc, call_daemon_rpc_with_limit, classify_event,
+        constant_time_eq, delete_thread, export_workspace, extract_request_token,
+        extract_ws_protocol_token, import_thread, import_transcript, interrupt_thread,
+        is_event_notification, list_all_threads, list_drawings, parse_args_from, render_metrics,
+        render_thread_markdown, send_message, start_thread, AuthAudit, DaemonConn, DaemonPool,
+        DeleteThreadRequest, DrawingsQuery, EventCounts, EventKind, EventStats, FeedCursor,
+        GatewayConfig, GatewaySettings, GatewayState, IdempotencyCache, ImportThreadRequest,
+        InterruptThreadRequest, Semaphore, ThreadFeedQuery, AUTH_FAILURE_LIMIT,
+        DEFAULT_DAEMON_ADDR, DEFAULT_DAEMON_POOL_SIZE, DEFAULT_DAEMON_TIMEOUT,
+        DEFAULT_MAX_EXPORT_THREAD_BYTES, DEFAULT_MAX_RESPONSE_BYTES, DEFAULT_WS_RECONNECT_ATTEMPTS,
+        EXPORT_RETRY_AFTER_SECS, IDEMPOTENCY_MAX_ENTRIES, IDEMPOTENCY_TTL, MAX_CONCURRENT_EXPORTS,
+        MAX_FEED_EMPTY_PAGES, MAX_WS_PENDING_RPCS, STATS_BUCKET_SECS,
+    };
+    use axum::extract::{Json, Query, State};
+    use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+    use axum::response::Response;
+    use serde_json::{json, Value};
+    use std::future::Future;
+    use std::io::{Cursor, Read};
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::sync::Notify;
+
+    fn run_async<F: Future<Output = ()>>(future: F) {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("create runtime");
+        runtime.block_on(future);
+    }
+
+    /// Starts a line-delimited JSON-RPC server that answers every request with `handler`.
+    async fn spawn_mock_daemon<F, Fut>(handler: F) -> String
+    where
+        F: Fn(String, Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, String>> + Send + 'static,
+    {
+        spawn_counting_mock_daemon(handler).await.0
+    }
+
+    /// Like `spawn_mock_daemon`, also counting accepted connections. It accepts
+    /// `unsubscribe_events` itself, as the real daemon does. After
+    /// running the handler, `mock/disconnect` closes its connection without
+    /// answering and `mock/hangup` closes it right after answering.
+    async fn spawn_counting_mock_daemon<F, Fut>(handler: F) -> (String, Arc<AtomicUsize>)
+    where
+        F: Fn(String, Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, String>> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock daemon");
+        let addr = listener.local_addr().expect("mock daemon addr").to_string();
+        let handler = Arc::new(handler);
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = stream.into_split();
+                    let mut lines = BufReader::new(reader).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                            continue;
+                        };
+                        let id = message.get("id").cloned().unwrap_or(Value::Null);
+                        let method = message
+                            .get("method")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string();
+                        let params = message.get("params").cloned().unwrap_or(Value::Null);
+                        let disconnect = method == "mock/disconnect";
+                        let hangup = method == "mock/hangup";
+                        let outcome = if method == "unsubscribe_events" {
+                            Ok(json!({ "ok": true }))
+                        } else {
+                            handler(method, params).await
+                        };
+                        if disconnect {
+                            break;
+                        }
+                        let response = match outcome {
+                            Ok(result) => json!({ "id": id, "result": result }),
+                            Err(error) => json!({ "id": id, "error": { "message": error } }),
+                        };
+                        let payload = format!("{response}\n");
+                        if writer.write_all(payload.as_bytes()).await.is_err() || hangup {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        (addr, connections)
+    }
+
+    fn test_config(daemon_addr: String) -> GatewayConfig {
+        GatewayConfig {
+            listen: "127.0.0.1:0".parse().expect("listen addr"),
+            daemon_addr,
+            daemon_token: None,
+            daemon_pool_size: DEFAULT_DAEMON_POOL_SIZE,
+            daemon_timeout: DEFAULT_DAEMON_TIMEOUT,
+            ws_reconnect_attempts: DEFAULT_WS_RECONNECT_ATTEMPTS,
+            api_token: Some("secret".to_string()),
+            audit_successes: false,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            max_export_thread_bytes: DEFAULT_MAX_EXPORT_THREAD_BYTES,
+        }
+    }
+
+    fn test_state(daemon_addr: String, idempotency: IdempotencyCache) -> GatewayState {
+        state_with_config(test_config(daemon_addr), idempotency)
+    }
+
+    fn state_with_config(config: GatewayConfig, idempotency: IdempotencyCache) -> GatewayState {
+        let config = Arc::new(config);
+        let request_ids = Arc::new(AtomicU64::new(1));
+        GatewayState {
+            daemon: Arc::new(DaemonPool::new(config.clone(), request_ids.clone())),
+            request_ids,
+            config,
+            idempotency: Arc::new(idempotency),
+            stats: Arc::new(EventStats::default()),
+            audit: Arc::new(AuthAudit::new()),
+            exports: Arc::new(Semaphore::new(MAX_CONCURRENT_EXPORTS)),
+        }
+    }
+
+    fn authed_headers(idempotency_key: Option<&'static str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secret"),
+        );
+        if let Some(key) = idempotency_key {
+            headers.insert("idempotency-key", HeaderValue::from_static(key));
+        }
+        headers
+    }
+
+    fn send_request() -> Json<super::SendMessageRequest> {
+        Json(
+            serde_json::from_value(json!({
+                "workspaceId": "ws-1",
+                "threadId": "thread-1",
+                "text": "hello",
+            }))
+            .expect("send request"),
+        )
+    }
+
+    async fn response_json(response: Response) -> (StatusCode, HeaderMap, Value) {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        let body = serde_json::from_slice(&bytes).expect("json body");
+        (status, headers, body)
+    }
+
+    fn counting_daemon(
+        calls: Arc<AtomicUsize>,
+        outcome: Result<Value, String>,
+    ) -> impl Fn(String, Value) -> std::future::Ready<Result<Value, String>> {
+        move |method, _params| {
+            if method == "send_user_message" {
+                calls.fetch_add(1, Ordering::SeqCst);
+            }
+            std::future::ready(outcome.clone())
+        }
+    }
 
     #[test]
     fn extracts_bearer_token() {
@@ -954,6 +3684,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn extracts_token_from_websocket_subprotocol() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static("codex-monitor.v1, codex-monitor.token.proto-token"),
+        );
+        assert_eq!(extract_ws_protocol_token(&headers), Some("proto-token"));
+
+        headers.insert(
+            header::SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static("codex-monitor.v1"),
+        );
+        assert_eq!(extract_ws_protocol_token(&headers), None);
+    }
+
     #[test]
     fn event_detection_requires_method_and_no_id() {
         assert!(is_event_notification(&json!({
@@ -966,4 +3712,1695 @@ mod tests {
             "result": {"ok": true},
         })));
     }
+
+    #[test]
+    fn idempotent_send_replays_stored_response() {
+        run_async(async {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let addr =
+                spawn_mock_daemon(counting_daemon(calls.clone(), Ok(json!({ "turn": "t-1" }))))
+                    .await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let first = send_message(
+                State(state.clone()),
+                authed_headers(Some("retry-1")),
+                send_request(),
+            )
+            .await
+            .expect("first send");
+            let (status, headers, first_body) = response_json(first).await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(headers.get("idempotent-replay").is_none());
+
+            let second = send_message(
+                State(state.clone()),
+                authed_headers(Some("retry-1")),
+                send_request(),
+            )
+            .await
+            .expect("replayed send");
+            let (status, headers, second_body) = response_json(second).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(
+                headers
+                    .get("idempotent-replay")
+                    .and_then(|v| v.to_str().ok()),
+                Some("true")
+            );
+            assert_eq!(first_body, second_body);
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+        });
+    }
+
+    #[test]
+    fn idempotent_send_replays_daemon_errors() {
+        run_async(async {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let addr =
+                spawn_mock_daemon(counting_daemon(calls.clone(), Err("boom".to_string()))).await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let first = send_message(
+                State(state.clone()),
+                authed_headers(Some("retry-err")),
+                send_request(),
+            )
+            .await
+            .expect_err("daemon error");
+            assert_eq!(first.status, StatusCode::BAD_GATEWAY);
+
+            let second = send_message(
+                State(state.clone()),
+                authed_headers(Some("retry-err")),
+                send_request(),
+            )
+            .await
+            .expect("replayed error");
+            let (status, headers, body) = response_json(second).await;
+            assert_eq!(status, StatusCode::BAD_GATEWAY);
+            assert!(headers.get("idempotent-replay").is_some());
+            assert_eq!(body, json!({ "error": "boom" }));
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+        });
+    }
+
+    #[test]
+    fn idempotent_send_rejects_duplicate_while_in_flight() {
+        run_async(async {
+            let started = Arc::new(Notify::new());
+            let release = Arc::new(Notify::new());
+            let daemon_started = started.clone();
+            let daemon_release = release.clone();
+            let addr = spawn_mock_daemon(move |_method, _params| {
+                let started = daemon_started.clone();
+                let release = daemon_release.clone();
+                async move {
+                    started.notify_one();
+                    release.notified().await;
+                    Ok(json!({ "ok": true }))
+                }
+            })
+            .await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let first = tokio::spawn(send_message(
+                State(state.clone()),
+                authed_headers(Some("slow-1")),
+                send_request(),
+            ));
+            started.notified().await;
+
+            let duplicate = send_message(
+                State(state.clone()),
+                authed_headers(Some("slow-1")),
+                send_request(),
+            )
+            .await
+            .expect_err("in-flight duplicate");
+            assert_eq!(duplicate.status, StatusCode::CONFLICT);
+            assert_eq!(duplicate.code, Some("request_in_flight"));
+
+            release.notify_one();
+            let first = first.await.expect("join").expect("first send");
+            assert_eq!(first.status(), StatusCode::OK);
+        });
+    }
+
+    #[test]
+    fn idempotency_entries_expire_after_ttl() {
+        run_async(async {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let addr =
+                spawn_mock_daemon(counting_daemon(calls.clone(), Ok(json!({ "ok": true })))).await;
+            let state = test_state(addr, IdempotencyCache::new(Duration::from_millis(20), 16));
+
+            for _ in 0..2 {
+                let response = send_message(
+                    State(state.clone()),
+                    authed_headers(Some("expiring")),
+                    send_request(),
+                )
+                .await
+                .expect("send");
+                assert!(response.headers().get("idempotent-replay").is_none());
+                tokio::time::sleep(Duration::from_millis(40)).await;
+            }
+            assert_eq!(calls.load(Ordering::SeqCst), 2);
+        });
+    }
+
+    #[test]
+    fn idempotency_keys_are_scoped_per_route() {
+        run_async(async {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let addr =
+                spawn_mock_daemon(counting_daemon(calls.clone(), Ok(json!({ "ok": true })))).await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            start_thread(
+                State(state.clone()),
+                authed_headers(Some("shared")),
+                Json(serde_json::from_value(json!({ "workspaceId": "ws-1" })).expect("start")),
+            )
+            .await
+            .expect("start");
+            let response = send_message(
+                State(state.clone()),
+                authed_headers(Some("shared")),
+                send_request(),
+            )
+            .await
+            .expect("send");
+
+            assert!(response.headers().get("idempotent-replay").is_none());
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+        });
+    }
+
+    #[test]
+    fn rejects_malformed_idempotency_key() {
+        run_async(async {
+            let state = test_state(
+                "127.0.0.1:9".to_string(),
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+            let mut headers = authed_headers(None);
+            headers.insert(
+                "idempotency-key",
+                HeaderValue::from_str(&"k".repeat(129)).expect("header value"),
+            );
+
+            let error = send_message(State(state), headers, send_request())
+                .await
+                .expect_err("malformed key");
+            assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        });
+    }
+
+    fn thread_with_turns(thread_id: &str) -> Value {
+        json!({
+            "id": thread_id,
+            "preview": format!("Preview {thread_id}"),
+            "turns": [{
+                "items": [
+                    {
+                        "type": "userMessage",
+                        "content": [{ "type": "text", "text": format!("question {thread_id}") }],
+                    },
+                    { "type": "commandExecution", "command": "ls" },
+                    { "type": "agentMessage", "text": format!("answer {thread_id}") },
+                ],
+            }],
+        })
+    }
+
+    #[test]
+    fn renders_thread_markdown_with_messages_only() {
+        let markdown = render_thread_markdown(&thread_with_turns("t-1"));
+        assert!(markdown.starts_with("# Preview t-1\n"));
+        assert!(markdown.contains("## User\n\nquestion t-1\n"));
+        assert!(markdown.contains("## Assistant\n\nanswer t-1\n"));
+        assert!(!markdown.contains("ls"));
+    }
+
+    #[test]
+    fn exports_workspace_threads_as_zip_archive() {
+        run_async(async {
+            let addr = spawn_mock_daemon(|method, params| async move {
+                match method.as_str() {
+                    "list_workspaces" => Ok(json!([{ "id": "ws-1", "name": "Demo" }])),
+                    "ping" => Ok(json!({ "ok": true, "version": "9.9.9" })),
+                    "list_threads" => {
+                        if params.get("cursor").and_then(Value::as_str) == Some("page-2") {
+                            Ok(json!({ "data": [{ "id": "t-3" }], "nextCursor": null }))
+                        } else {
+                            Ok(json!({
+                                "data": [{ "id": "t-1" }, { "id": "t-2" }],
+                                "nextCursor": "page-2",
+                            }))
+                        }
+                    }
+                    "resume_thread" => {
+                        let thread_id = params
+                            .get("threadId")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default();
+                        Ok(json!({ "result": { "thread": thread_with_turns(thread_id) } }))
+                    }
+                    other => Err(format!("unexpected method {other}")),
+                }
+            })
+            .await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let response = export_workspace(
+                State(state),
+                authed_headers(None),
+                Query(
+                    serde_json::from_value(json!({ "workspaceId": "ws-1" })).expect("export query"),
+                ),
+            )
+            .await
+            .expect("export response");
+            assert_eq!(
+                response.headers().get(header::CONTENT_TYPE),
+                Some(&HeaderValue::from_static("application/zip"))
+            );
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("archive bytes");
+
+            let mut archive = zip::ZipArchive::new(Cursor::new(bytes.to_vec())).expect("zip");
+            let mut names: Vec<String> = archive.file_names().map(ToString::to_string).collect();
+            names.sort();
+            assert_eq!(
+                names,
+                vec![
+                    "manifest.json",
+                    "threads/0001-t-1.json",
+                    "threads/0001-t-1.md",
+                    "threads/0002-t-2.json",
+                    "threads/0002-t-2.md",
+                    "threads/0003-t-3.json",
+                    "threads/0003-t-3.md",
+                ]
+            );
+
+            let mut markdown = String::new();
+            archive
+                .by_name("threads/0003-t-3.md")
+                .expect("markdown entry")
+                .read_to_string(&mut markdown)
+                .expect("read markdown");
+            assert!(markdown.contains("answer t-3"));
+
+            let mut manifest = String::new();
+            archive
+                .by_name("manifest.json")
+                .expect("manifest entry")
+                .read_to_string(&mut manifest)
+                .expect("read manifest");
+            let manifest: Value = serde_json::from_str(&manifest).expect("manifest json");
+            assert_eq!(manifest["threadCount"], 3);
+            assert_eq!(manifest["exportedCount"], 3);
+            assert_eq!(manifest["daemonVersion"], "9.9.9");
+            assert_eq!(manifest["truncated"], false);
+            assert_eq!(manifest["failures"], json!([]));
+        });
+    }
+
+    #[test]
+    fn export_records_threads_over_the_size_limit_as_failures() {
+        run_async(async {
+            let addr = spawn_mock_daemon(|method, params| async move {
+                match method.as_str() {
+                    "list_workspaces" => Ok(json!([{ "id": "ws-1", "name": "Demo" }])),
+                    "ping" => Ok(json!({ "ok": true })),
+                    "list_threads" => Ok(json!({
+                        "data": [{ "id": "t-1" }, { "id": "t-2" }],
+                        "nextCursor": null,
+                    })),
+                    "resume_thread" => {
+                        let thread_id = params["threadId"].as_str().unwrap_or_default();
+                        let mut thread = thread_with_turns(thread_id);
+                        if thread_id == "t-2" {
+                            thread["preview"] = json!("x".repeat(4096));
+                        }
+                        Ok(json!({ "thread": thread }))
+                    }
+                    other => Err(format!("unexpected method {other}")),
+                }
+            })
+            .await;
+            let mut config = test_config(addr);
+            config.max_export_thread_bytes = 2048;
+            let state = state_with_config(
+                config,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let response = export_workspace(
+                State(state),
+                authed_headers(None),
+                Query(
+                    serde_json::from_value(json!({ "workspaceId": "ws-1" })).expect("export query"),
+                ),
+            )
+            .await
+            .expect("export response");
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("archive bytes");
+
+            let mut archive = zip::ZipArchive::new(Cursor::new(bytes.to_vec())).expect("zip");
+            assert!(archive.by_name("threads/0001-t-1.md").is_ok());
+            assert!(archive.by_name("threads/0002-t-2.md").is_err());
+            let mut manifest = String::new();
+            archive
+                .by_name("manifest.json")
+                .expect("manifest entry")
+                .read_to_string(&mut manifest)
+                .expect("read manifest");
+            let manifest: Value = serde_json::from_str(&manifest).expect("manifest json");
+            assert_eq!(manifest["exportedCount"], 1);
+            assert_eq!(manifest["failures"][0]["threadId"], "t-2");
+            assert!(manifest["failures"][0]["error"]
+                .as_str()
+                .is_some_and(|error| error.contains("2048 byte export limit")));
+        });
+    }
+
+    #[test]
+    fn concurrent_exports_are_limited() {
+        run_async(async {
+            let addr = spawn_mock_daemon(|method, _params| async move {
+                match method.as_str() {
+                    "list_workspaces" => Ok(json!([{ "id": "ws-1", "name": "Demo" }])),
+                    "ping" => Ok(json!({ "ok": true })),
+                    "list_threads" => Ok(json!({ "data": [{ "id": "t-1" }], "nextCursor": null })),
+                    "resume_thread" => std::future::pending().await,
+                    other => Err(format!("unexpected method {other}")),
+                }
+            })
+            .await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+            let export = || {
+                export_workspace(
+                    State(state.clone()),
+                    authed_headers(None),
+                    Query(
+                        serde_json::from_value(json!({ "workspaceId": "ws-1" }))
+                            .expect("export query"),
+                    ),
+                )
+            };
+
+            // The running exports hang on `resume_thread` and keep their permits.
+            let mut running = Vec::new();
+            for _ in 0..MAX_CONCURRENT_EXPORTS {
+                let response = export().await.expect("export response");
+                assert_eq!(response.status(), StatusCode::OK);
+                running.push(response);
+            }
+
+            let response = export().await.expect("throttled response");
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(
+                response.headers().get(header::RETRY_AFTER),
+                Some(&HeaderValue::from(EXPORT_RETRY_AFTER_SECS))
+            );
+        });
+    }
+
+    #[test]
+    fn export_skips_threads_from_other_workspaces() {
+        run_async(async {
+            let addr = spawn_mock_daemon(|method, params| async move {
+                match method.as_str() {
+                    "list_workspaces" => Ok(json!([
+                        { "id": "ws-1", "name": "Demo", "path": "/repo/demo" },
+                    ])),
+                    "ping" => Ok(json!({ "ok": true })),
+                    "list_threads" => {
+                        if params.get("cursor").and_then(Value::as_str) == Some("page-2") {
+                            Ok(json!({
+                                "data": [
+                                    { "id": "x-2", "cwd": "C:\\other" },
+                                    { "id": "t-2", "cwd": "/repo/demo" },
+                                    { "id": "t-3", "cwd": "/repo/demo" },
+                                ],
+                                "nextCursor": null,
+                            }))
+                        } else {
+                            Ok(json!({
+                                "data": [
+                                    { "id": "t-1", "cwd": "/repo/demo/" },
+                                    { "id": "x-1", "cwd": "/repo/other" },
+                                ],
+                                "nextCursor": "page-2",
+                            }))
+                        }
+                    }
+                    "resume_thread" => {
+                        let thread_id = params
+                            .get("threadId")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default();
+                        if thread_id.starts_with("x-") {
+                            return Err(format!("resumed foreign thread {thread_id}"));
+                        }
+                        Ok(json!({ "thread": thread_with_turns(thread_id) }))
+                    }
+                    other => Err(format!("unexpected method {other}")),
+                }
+            })
+            .await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let response = export_workspace(
+                State(state),
+                authed_headers(None),
+                Query(
+                    serde_json::from_value(json!({ "workspaceId": "ws-1", "maxThreads": 2 }))
+                        .expect("export query"),
+                ),
+            )
+            .await
+            .expect("export response");
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("archive bytes");
+
+            let mut archive = zip::ZipArchive::new(Cursor::new(bytes.to_vec())).expect("zip");
+            let mut names: Vec<String> = archive.file_names().map(ToString::to_string).collect();
+            names.sort();
+            assert_eq!(
+                names,
+                vec![
+                    "manifest.json",
+                    "threads/0001-t-1.json",
+                    "threads/0001-t-1.md",
+                    "threads/0002-t-2.json",
+                    "threads/0002-t-2.md",
+                ]
+            );
+
+            let mut manifest = String::new();
+            archive
+                .by_name("manifest.json")
+                .expect("manifest entry")
+                .read_to_string(&mut manifest)
+                .expect("read manifest");
+            let manifest: Value = serde_json::from_str(&manifest).expect("manifest json");
+            assert_eq!(manifest["threadCount"], 2);
+            assert_eq!(manifest["truncated"], true);
+            assert_eq!(manifest["failures"], json!([]));
+        });
+    }
+
+    #[test]
+    fn classifies_daemon_events_by_workspace_and_kind() {
+        let completed = json!({
+            "method": "app-server-event",
+            "params": {
+                "workspace_id": "ws-1",
+                "message": { "method": "turn/completed", "params": {} },
+            },
+        });
+        let error = json!({
+            "method": "app-server-event",
+            "params": { "workspace_id": "ws-1", "message": { "method": "error" } },
+        });
+        let terminal = json!({
+            "method": "terminal-output",
+            "params": { "workspaceId": "ws-2", "terminalId": "t", "data": "x" },
+        });
+
+        assert_eq!(
+            classify_event(&completed),
+            Some(("ws-1".to_string(), EventKind::TurnCompleted))
+        );
+        assert_eq!(
+            classify_event(&error),
+            Some(("ws-1".to_string(), EventKind::Error))
+        );
+        assert_eq!(
+            classify_event(&terminal),
+            Some(("ws-2".to_string(), EventKind::Other))
+        );
+        assert_eq!(classify_event(&json!({ "method": "ping" })), None);
+    }
+
+    #[test]
+    fn event_stats_bucket_by_hour_and_expire_after_two_days() {
+        let stats = EventStats::default();
+        let start = 1_700_000_000 - 1_700_000_000 % STATS_BUCKET_SECS;
+        stats.record("ws-1", EventKind::Other, start + 10);
+        stats.record("ws-1", EventKind::TurnCompleted, start + 20);
+        stats.record("ws-1", EventKind::Error, start + STATS_BUCKET_SECS + 5);
+
+        let snapshot = stats.snapshot(Some("ws-1"), start + STATS_BUCKET_SECS + 5);
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].buckets.len(), 2);
+        assert_eq!(
+            snapshot[0].buckets[0].counts,
+            EventCounts {
+                events: 2,
+                turns_completed: 1,
+                errors: 0,
+            }
+        );
+        assert_eq!(snapshot[0].totals.events, 3);
+
+        let later = start + STATS_BUCKET_SECS * 49 + 1;
+        let snapshot = stats.snapshot(None, later);
+        assert_eq!(snapshot[0].buckets.len(), 0);
+        assert_eq!(snapshot[0].totals.events, 3);
+        assert!(stats.snapshot(Some("ws-missing"), later).is_empty());
+    }
+
+    #[test]
+    fn renders_prometheus_counters_per_workspace() {
+        let totals = vec![(
+            "ws-1".to_string(),
+            EventCounts {
+                events: 4,
+                turns_completed: 2,
+                errors: 1,
+            },
+        )];
+        let output = render_metrics(&totals);
+        assert!(output.contains("# TYPE codex_gateway_events_total counter\n"));
+        assert!(output.contains("codex_gateway_events_total{workspace_id=\"ws-1\"} 4\n"));
+        assert!(output.contains("codex_gateway_turns_completed_total{workspace_id=\"ws-1\"} 2\n"));
+        assert!(output.contains("codex_gateway_errors_total{workspace_id=\"ws-1\"} 1\n"));
+    }
+
+    fn import_request(body: Value) -> ImportThreadRequest {
+        serde_json::from_value(body).expect("import request")
+    }
+
+    #[test]
+    fn import_transcript_validates_messages() {
+        let error = import_transcript(&import_request(json!({
+            "workspaceId": "ws-1",
+            "messages": [
+                { "role": "user", "text": "hi" },
+                { "role": "system", "text": "nope" },
+            ],
+        })))
+        .expect_err("invalid role");
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert!(error.message.contains("messages[1].role"));
+
+        let error = import_transcript(&import_request(json!({ "workspaceId": "ws-1" })))
+            .expect_err("empty transcript");
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+
+        let (messages, title) = import_transcript(&import_request(json!({
+            "workspaceId": "ws-1",
+            "thread": {
+                "id": "thread-9",
+                "name": "Exported",
+                "turns": [{ "items": [
+                    { "type": "userMessage", "content": [{ "type": "text", "text": "question" }] },
+                    { "type": "commandExecution", "command": "ls" },
+                    { "type": "agentMessage", "text": "answer" },
+                ]}],
+            },
+        })))
+        .expect("exported thread");
+        assert_eq!(title.as_deref(), Some("Exported"));
+        assert_eq!(
+            messages
+                .iter()
+                .map(|message| (message.role.as_str(), message.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("user", "question"), ("assistant", "answer")]
+        );
+    }
+
+    #[test]
+    fn import_thread_starts_names_and_seeds_thread() {
+        run_async(async {
+            let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let recorded = calls.clone();
+            let addr = spawn_mock_daemon(move |method, params| {
+                recorded
+                    .lock()
+                    .expect("calls lock")
+                    .push((method.clone(), params));
+                std::future::ready(match method.as_str() {
+                    "start_thread" => Ok(json!({ "thread": { "id": "thread-new" } })),
+                    _ => Ok(json!({ "ok": true })),
+                })
+            })
+            .await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let Json(body) = import_thread(
+                State(state),
+                authed_headers(None),
+                Json(import_request(json!({
+                    "workspaceId": "ws-1",
+                    "title": "Restored",
+                    "messages": [
+                        { "role": "user", "text": "hello" },
+                        { "role": "assistant", "text": "hi there" },
+                    ],
+                }))),
+            )
+            .await
+            .expect("import thread");
+            assert_eq!(body["threadId"], "thread-new");
+            assert_eq!(body["importedMessages"], 2);
+
+            let calls = calls.lock().expect("calls lock");
+            let methods: Vec<&str> = calls.iter().map(|(method, _)| method.as_str()).collect();
+            assert_eq!(
+                methods,
+                vec!["start_thread", "set_thread_name", "send_user_message"]
+            );
+            assert_eq!(calls[1].1["name"], "Restored");
+            let seed = calls[2].1["text"].as_str().expect("seed text");
+            assert!(seed.contains("## User\n\nhello"));
+            assert!(seed.contains("## Assistant\n\nhi there"));
+        });
+    }
+
+    #[test]
+    fn merged_feed_pages_across_workspaces() {
+        run_async(async {
+            let threads = Arc::new(std::sync::Mutex::new(json!({
+                "ws-a": [
+                    { "id": "a1", "updatedAt": 100 },
+                    { "id": "a2", "updatedAt": 80 },
+                    { "id": "a3", "updatedAt": 60 },
+                ],
+                "ws-b": [
+                    { "id": "b1", "updatedAt": 90 },
+                    { "id": "b2", "updatedAt": 70 },
+                ],
+                "ws-c": [],
+            })));
+            let failures = Arc::new(AtomicUsize::new(0));
+            let daemon_threads = threads.clone();
+            let daemon_failures = failures.clone();
+            let addr = spawn_mock_daemon(move |method, params| {
+                let threads = daemon_threads.lock().expect("threads lock").clone();
+                let failures = daemon_failures.clone();
+                async move {
+                    if method == "list_workspaces" {
+                        return Ok(json!([
+                            { "id": "ws-a", "name": "Alpha" },
+                            { "id": "ws-b", "name": "Beta" },
+                            { "id": "ws-c", "name": "Gamma" },
+                        ]));
+                    }
+                    let workspace_id = params["workspaceId"].as_str().unwrap_or_default();
+                    if workspace_id == "ws-c" && failures.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return Err("workspace offline".to_string());
+                    }
+                    let all = threads[workspace_id]
+                        .as_array()
+                        .cloned()
+                        .unwrap_or_default();
+                    let offset: usize = params["cursor"]
+                        .as_str()
+                        .and_then(|cursor| cursor.parse().ok())
+                        .unwrap_or(0);
+                    let limit = params["limit"].as_u64().unwrap_or(50) as usize;
+                    let end = (offset + limit).min(all.len());
+                    let next = (end < all.len()).then(|| end.to_string());
+                    Ok(json!({ "data": all[offset.min(end)..end], "nextCursor": next }))
+                }
+            })
+            .await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+            let page = |cursor: Option<String>| {
+                let state = state.clone();
+                async move {
+                    let Json(response) = list_all_threads(
+                        State(state),
+                        authed_headers(None),
+                        Query(ThreadFeedQuery {
+                            limit: Some(2),
+                            cursor,
+                        }),
+                    )
+                    .await
+                    .expect("feed page");
+                    response
+                }
+            };
+            let ids = |threads: &[Value]| -> Vec<String> {
+                threads
+                    .iter()
+                    .map(|entry| {
+                        entry["thread"]["id"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string()
+                    })
+                    .collect()
+            };
+
+            let first = page(None).await;
+            assert_eq!(ids(&first.threads), vec!["a1", "b1"]);
+            assert_eq!(first.threads[0]["workspaceName"], "Alpha");
+            assert_eq!(first.warnings.len(), 1);
+            assert_eq!(first.warnings[0].workspace_id, "ws-c");
+
+            // a3 jumps to the top; it must not reappear deeper or push a2 out.
+            threads.lock().expect("threads lock")["ws-a"] = json!([
+                { "id": "a3", "updatedAt": 200 },
+                { "id": "a1", "updatedAt": 100 },
+                { "id": "a2", "updatedAt": 80 },
+            ]);
+            let second = page(first.next_cursor).await;
+            assert_eq!(ids(&second.threads), vec!["a2", "b2"]);
+            assert!(second.warnings.is_empty());
+
+            assert!(second.next_cursor.is_none());
+        });
+    }
+
+    #[test]
+    fn merged_feed_lists_each_thread_under_its_own_workspace() {
+        run_async(async {
+            let addr = spawn_mock_daemon(|method, _params| async move {
+                if method == "list_workspaces" {
+                    return Ok(json!([
+                        { "id": "ws-a", "name": "Alpha", "path": "/repo/alpha" },
+                        { "id": "ws-b", "name": "Beta", "path": "C:\\repo\\beta\\" },
+                    ]));
+                }
+                // Every workspace shares the Codex home and sees the same list.
+                Ok(json!({
+                    "data": [
+                        { "id": "a1", "cwd": "/repo/alpha", "updatedAt": 100 },
+                        { "id": "o1", "cwd": "/repo/other", "updatedAt": 95 },
+                        { "id": "b1", "cwd": "C:\\repo\\beta", "updatedAt": 90 },
+                    ],
+                    "nextCursor": null,
+                }))
+            })
+            .await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let Json(response) = list_all_threads(
+                State(state),
+                authed_headers(None),
+                Query(ThreadFeedQuery {
+                    limit: None,
+                    cursor: None,
+                }),
+            )
+            .await
+            .expect("feed page");
+            let entries: Vec<(&str, &str)> = response
+                .threads
+                .iter()
+                .map(|entry| {
+                    (
+                        entry["thread"]["id"].as_str().unwrap_or_default(),
+                        entry["workspaceId"].as_str().unwrap_or_default(),
+                    )
+                })
+                .collect();
+            assert_eq!(entries, vec![("a1", "ws-a"), ("b1", "ws-b")]);
+            assert!(response.next_cursor.is_none());
+        });
+    }
+
+    #[test]
+    fn merged_feed_stops_on_stuck_cursors_and_empty_pages() {
+        run_async(async {
+            let stuck_calls = Arc::new(AtomicUsize::new(0));
+            let foreign_calls = Arc::new(AtomicUsize::new(0));
+            let (daemon_stuck, daemon_foreign) = (stuck_calls.clone(), foreign_calls.clone());
+            let addr = spawn_mock_daemon(move |method, params| {
+                let (stuck, foreign) = (daemon_stuck.clone(), daemon_foreign.clone());
+                async move {
+                    if method == "list_workspaces" {
+                        return Ok(json!([
+                            { "id": "ws-a", "path": "/repo/alpha" },
+                            { "id": "ws-b", "path": "/repo/beta" },
+                        ]));
+                    }
+                    if params["workspaceId"] == "ws-a" {
+                        stuck.fetch_add(1, Ordering::SeqCst);
+                        return Ok(json!({ "data": [], "nextCursor": "same" }));
+                    }
+                    let page = foreign.fetch_add(1, Ordering::SeqCst);
+                    Ok(json!({
+                        "data": [{ "id": format!("o{page}"), "cwd": "/repo/other", "updatedAt": 1 }],
+                        "nextCursor": (page + 1).to_string(),
+                    }))
+                }
+            })
+            .await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let Json(response) = tokio::time::timeout(
+                Duration::from_secs(5),
+                list_all_threads(
+                    State(state),
+                    authed_headers(None),
+                    Query(ThreadFeedQuery {
+                        limit: None,
+                        cursor: None,
+                    }),
+                ),
+            )
+            .await
+            .expect("feed returns")
+            .expect("feed page");
+            assert!(response.threads.is_empty());
+            assert_eq!(stuck_calls.load(Ordering::SeqCst), 2);
+            assert_eq!(foreign_calls.load(Ordering::SeqCst), MAX_FEED_EMPTY_PAGES);
+
+            let cursor = FeedCursor::decode(response.next_cursor.as_deref().expect("more pages"))
+                .expect("cursor");
+            assert!(cursor.positions["ws-a"].done);
+            assert_eq!(
+                cursor.positions["ws-b"].cursor.as_deref(),
+                Some(MAX_FEED_EMPTY_PAGES.to_string().as_str())
+            );
+        });
+    }
+
+    #[test]
+    fn constant_time_eq_matches_only_identical_tokens() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret-longer"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+
+    /// Sends a bare HTTP/1.1 request and returns the status code and raw response.
+    async fn raw_http(
+        addr: std::net::SocketAddr,
+        path: &str,
+        token: Option<&str>,
+    ) -> (u16, String) {
+        let mut stream = tokio::net::TcpStream::connect(addr)
+            .await
+            .expect("connect gateway");
+        let auth = token
+            .map(|token| format!("Authorization: Bearer {token}\r\n"))
+            .unwrap_or_default();
+        let request =
+            format!("GET {path} HTTP/1.1\r\nHost: test\r\n{auth}Connection: close\r\n\r\n");
+        stream
+            .write_all(request.as_bytes())
+            .await
+            .expect("write request");
+        let mut response = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut stream, &mut response)
+            .await
+            .expect("read response");
+        let status = response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .unwrap_or(0);
+        (status, response)
+    }
+
+    #[test]
+    fn auth_failures_are_audited_and_throttled() {
+        run_async(async {
+            let state = test_state(
+                "127.0.0.1:9".to_string(),
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+            let audit = state.audit.clone();
+            let addr = spawn_gateway(state).await;
+
+            for _ in 0..3 {
+                let (status, body) = raw_http(addr, "/api/audit", None).await;
+                assert_eq!(status, 401);
+                assert!(body.contains("missing_token"));
+            }
+            for _ in 0..AUTH_FAILURE_LIMIT {
+                let (status, body) = raw_http(addr, "/api/audit", Some("guess")).await;
+                assert_eq!(status, 401);
+                assert!(!body.contains("secret"));
+            }
+
+            let (status, body) = raw_http(addr, "/api/audit", Some("secret")).await;
+            assert_eq!(status, 429);
+            assert!(body.to_ascii_lowercase().contains("retry-after"));
+
+            let entries =
+                serde_json::to_value(audit.recent(AUTH_FAILURE_LIMIT + 4)).expect("audit entries");
+            let entries = entries.as_array().expect("entries array");
+            assert_eq!(entries.len(), AUTH_FAILURE_LIMIT + 4);
+            assert_eq!(entries[0]["outcome"], "throttled");
+            assert_eq!(entries[1]["outcome"], "failure");
+            assert_eq!(entries[1]["reason"], "invalid_token");
+            assert_eq!(entries[1]["route"], "/api/audit");
+            assert_eq!(entries[1]["peer"], "127.0.0.1");
+            assert_eq!(entries[AUTH_FAILURE_LIMIT + 3]["reason"], "missing_token");
+        });
+    }
+
+    #[test]
+    fn oversized_daemon_responses_are_rejected_while_reading() {
+        run_async(async {
+            let (addr, connections) = spawn_counting_mock_daemon(|method, _params| async move {
+                match method.as_str() {
+                    "big" => Ok(json!({ "text": "x".repeat(4096) })),
+                    _ => Ok(json!({ "ok": true })),
+                }
+            })
+            .await;
+            let mut config = test_config(addr);
+            config.max_response_bytes = 1024;
+            let state = state_with_config(
+                config,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let error = call_daemon_rpc(&state.daemon, "big", Value::Null)
+                .await
+                .expect_err("too big");
+            assert_eq!(error.status, StatusCode::PAYLOAD_TOO_LARGE);
+            assert!(error.message.contains("1024 byte"));
+
+            let result = call_daemon_rpc_with_limit(&state.daemon, "big", Value::Null, usize::MAX)
+                .await
+                .expect("raised limit");
+            assert_eq!(result["text"].as_str().map(str::len), Some(4096));
+            call_daemon_rpc(&state.daemon, "ping", Value::Null)
+                .await
+                .expect("ping after oversized response");
+            // The connection that tripped the limit was dropped.
+            assert_eq!(connections.load(Ordering::SeqCst), 2);
+        });
+    }
+
+    #[test]
+    fn daemon_pool_reuses_connections_and_evicts_broken_ones() {
+        run_async(async {
+            let disconnects = Arc::new(AtomicUsize::new(0));
+            let (addr, connections) = {
+                let disconnects = disconnects.clone();
+                spawn_counting_mock_daemon(move |method, _params| {
+                    if method == "mock/disconnect" {
+                        disconnects.fetch_add(1, Ordering::SeqCst);
+                    }
+                    std::future::ready(Ok(json!({ "ok": true })))
+                })
+                .await
+            };
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            for _ in 0..3 {
+                call_daemon_rpc(&state.daemon, "ping", Value::Null)
+                    .await
+                    .expect("ping");
+            }
+            assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+            // A connection the daemon closed while parked is replaced before
+            // the next request is sent.
+            call_daemon_rpc(&state.daemon, "mock/hangup", Value::Null)
+                .await
+                .expect("hangup");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            call_daemon_rpc(&state.daemon, "ping", Value::Null)
+                .await
+                .expect("ping after hangup");
+            assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+            // A connection that breaks after the request was sent is dropped,
+            // and the request is not sent again.
+            let error = call_daemon_rpc(&state.daemon, "mock/disconnect", Value::Null)
+                .await
+                .expect_err("disconnect");
+            assert_eq!(error.status, StatusCode::BAD_GATEWAY);
+            assert_eq!(disconnects.load(Ordering::SeqCst), 1);
+            assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+            call_daemon_rpc(&state.daemon, "ping", Value::Null)
+                .await
+                .expect("ping after eviction");
+            assert_eq!(connections.load(Ordering::SeqCst), 3);
+        });
+    }
+
+    #[test]
+    fn connections_still_receiving_events_are_not_parked() {
+        run_async(async {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("bind mock daemon");
+            let addr = listener.local_addr().expect("mock daemon addr").to_string();
+            let connections = Arc::new(AtomicUsize::new(0));
+            let accepted = connections.clone();
+            // A daemon that predates `unsubscribe_events`.
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    accepted.fetch_add(1, Ordering::SeqCst);
+                    tokio::spawn(async move {
+                        let (reader, mut writer) = stream.into_split();
+                        let mut lines = BufReader::new(reader).lines();
+                        while let Ok(Some(line)) = lines.next_line().await {
+                            let request: Value = serde_json::from_str(&line).expect("request");
+                            let response = if request["method"] == "unsubscribe_events" {
+                                json!({ "id": request["id"], "error": { "message": "unknown method" } })
+                            } else {
+                                json!({ "id": request["id"], "result": {} })
+                            };
+                            let _ = writer.write_all(format!("{response}\n").as_bytes()).await;
+                        }
+                    });
+                }
+            });
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            for _ in 0..2 {
+                call_daemon_rpc(&state.daemon, "ping", Value::Null)
+                    .await
+                    .expect("ping");
+            }
+            assert_eq!(connections.load(Ordering::SeqCst), 2);
+        });
+    }
+
+    #[test]
+    fn interleaved_requests_resolve_by_id() {
+        run_async(async {
+            let addr = spawn_mock_daemon(|method, params| async move {
+                Ok(json!({ "method": method, "echo": params }))
+            })
+            .await;
+            let state = test_state(
+                addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+            let mut conn = DaemonConn::open(&state.config, &state.request_ids)
+                .await
+                .expect("open connection");
+
+            let first = state.request_ids.fetch_add(1, Ordering::Relaxed);
+            let second = state.request_ids.fetch_add(1, Ordering::Relaxed);
+            assert_ne!(first, second);
+            conn.send(first, "list_workspaces", json!({ "n": 1 }))
+                .await
+                .expect("send first");
+            conn.send(second, "list_threads", json!({ "n": 2 }))
+                .await
+                .expect("send second");
+
+            let second_result = conn.recv(second).await.expect("recv second");
+            let first_result = conn.recv(first).await.expect("recv first");
+            assert_eq!(
+                second_result,
+                Ok(json!({ "method": "list_threads", "echo": { "n": 2 } }))
+            );
+            assert_eq!(
+                first_result,
+                Ok(json!({ "method": "list_workspaces", "echo": { "n": 1 } }))
+            );
+        });
+    }
+
+    #[test]
+    fn hung_daemon_calls_time_out_and_drop_the_connection() {
+        run_async(async {
+            let (addr, connections) = spawn_counting_mock_daemon(|method, _params| async move {
+                if method == "hang" {
+                    std::future::pending::<()>().await;
+                }
+                Ok(json!({ "ok": true }))
+            })
+            .await;
+            let mut config = test_config(addr);
+            config.daemon_timeout = Duration::from_millis(100);
+            let state = state_with_config(
+                config,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let error = call_daemon_rpc(&state.daemon, "hang", Value::Null)
+                .await
+                .expect_err("timeout");
+            assert_eq!(error.status, StatusCode::GATEWAY_TIMEOUT);
+
+            call_daemon_rpc(&state.daemon, "ping", Value::Null)
+                .await
+                .expect("ping after timeout");
+            assert_eq!(connections.load(Ordering::SeqCst), 2);
+        });
+    }
+
+    /// Serves `state` on an ephemeral port and returns the bound address.
+    async fn spawn_gateway(state: GatewayState) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind gateway");
+        let addr = listener.local_addr().expect("gateway addr");
+        let app = build_router(state).into_make_service_with_connect_info::<std::net::SocketAddr>();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        addr
+    }
+
+    async fn next_ws_json(
+        socket: &mut tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+    ) -> Value {
+        use futures_util::StreamExt;
+        loop {
+            let frame = tokio::time::timeout(Duration::from_secs(5), socket.next())
+                .await
+                .expect("ws frame in time")
+                .expect("ws open")
+                .expect("ws frame");
+            if let tokio_tungstenite::tungstenite::Message::Text(text) = frame {
+                return serde_json::from_str(&text).expect("ws json");
+            }
+        }
+    }
+
+    #[test]
+    fn websocket_reconnects_to_daemon_after_stream_drops() {
+        run_async(async {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("bind mock daemon");
+            let daemon_addr = listener.local_addr().expect("mock daemon addr").to_string();
+            tokio::spawn(async move {
+                let mut accepted = 0;
+                while let Ok((stream, _)) = listener.accept().await {
+                    accepted += 1;
+                    let first = accepted == 1;
+                    tokio::spawn(async move {
+                        let (reader, mut writer) = stream.into_split();
+                        let mut lines = BufReader::new(reader).lines();
+                        let Ok(Some(line)) = lines.next_line().await else {
+                            return;
+                        };
+                        let request: Value = serde_json::from_str(&line).expect("ping");
+                        let reply = format!("{}\n", json!({ "id": request["id"], "result": {} }));
+                        let _ = writer.write_all(reply.as_bytes()).await;
+                        if first {
+                            return;
+                        }
+                        let event = json!({
+                            "method": "app-server-event",
+                            "params": { "workspace_id": "ws-1", "message": {} },
+                        });
+                        let _ = writer.write_all(format!("{event}\n").as_bytes()).await;
+                        while let Ok(Some(_)) = lines.next_line().await {}
+                    });
+                }
+            });
+
+            let addr = spawn_gateway(test_state(
+                daemon_addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            ))
+            .await;
+            let (mut socket, _) =
+                tokio_tungstenite::connect_async(format!("ws://{addr}/ws/events?token=secret"))
+                    .await
+                    .expect("connect websocket");
+
+            let ready = next_ws_json(&mut socket).await;
+            assert_eq!(ready["type"], "gateway/ready");
+            assert_eq!(ready["reconnected"], false);
+            let reconnecting = next_ws_json(&mut socket).await;
+            assert_eq!(reconnecting["type"], "gateway/reconnecting");
+            assert_eq!(reconnecting["attempt"], 1);
+            let ready = next_ws_json(&mut socket).await;
+            assert_eq!(ready["type"], "gateway/ready");
+            assert_eq!(ready["reconnected"], true);
+            let event = next_ws_json(&mut socket).await;
+            assert_eq!(event["method"], "app-server-event");
+        });
+    }
+
+    #[test]
+    fn websocket_relays_rpc_responses() {
+        run_async(async {
+            let daemon_addr = spawn_mock_daemon(|method, _params| async move {
+                match method.as_str() {
+                    "list_workspaces" => Ok(json!([{ "id": "ws-1", "name": "alpha" }])),
+                    "ping" => Ok(json!({})),
+                    other => Err(format!("unknown method `{other}`")),
+                }
+            })
+            .await;
+            let addr = spawn_gateway(test_state(
+                daemon_addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            ))
+            .await;
+            let (mut socket, _) =
+                tokio_tungstenite::connect_async(format!("ws://{addr}/ws/events?token=secret"))
+                    .await
+                    .expect("connect websocket");
+            assert_eq!(next_ws_json(&mut socket).await["type"], "gateway/ready");
+
+            use futures_util::SinkExt;
+            let request = json!({ "id": 7, "method": "list_workspaces", "params": {} });
+            socket
+                .send(tokio_tungstenite::tungstenite::Message::Text(
+                    request.to_string().into(),
+                ))
+                .await
+                .expect("send rpc");
+            let response = next_ws_json(&mut socket).await;
+            assert_eq!(response["type"], "rpc/response");
+            assert_eq!(response["id"], 7);
+            assert_eq!(response["result"][0]["id"], "ws-1");
+
+            let request = json!({ "id": "b", "method": "nope" });
+            socket
+                .send(tokio_tungstenite::tungstenite::Message::Text(
+                    request.to_string().into(),
+                ))
+                .await
+                .expect("send rpc");
+            let error = next_ws_json(&mut socket).await;
+            assert_eq!(error["type"], "rpc/error");
+            assert_eq!(error["id"], "b");
+        });
+    }
+
+    #[test]
+    fn drawings_fetch_workspaces_concurrently_in_order() {
+        run_async(async {
+            let in_flight = Arc::new(AtomicUsize::new(0));
+            let peak = Arc::new(AtomicUsize::new(0));
+            let daemon_addr = {
+                let in_flight = in_flight.clone();
+                let peak = peak.clone();
+                spawn_mock_daemon(move |method, params| {
+                    let in_flight = in_flight.clone();
+                    let peak = peak.clone();
+                    async move {
+                        if method == "list_workspaces" {
+                            return Ok(json!([
+                                { "id": "ws-0" },
+                                { "id": "ws-1" },
+                                { "id": "ws-2" },
+                                { "id": "ws-3" },
+                            ]));
+                        }
+                        let workspace_id = params["workspaceId"].as_str().unwrap_or_default();
+                        let index: u64 = workspace_id[3..].parse().expect("index");
+                        let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        // Earlier workspaces answer last.
+                        tokio::time::sleep(Duration::from_millis(40 * (4 - index))).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        if workspace_id == "ws-2" {
+                            return Err("workspace offline".to_string());
+                        }
+                        Ok(json!({ "data": [{ "id": format!("{workspace_id}-t") }] }))
+                    }
+                })
+                .await
+            };
+            let state = test_state(
+                daemon_addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let Json(response) = list_drawings(
+                State(state),
+                authed_headers(None),
+                Query(DrawingsQuery::default()),
+            )
+            .await
+            .expect("drawings");
+
+            assert!(peak.load(Ordering::SeqCst) > 1);
+            let ids: Vec<_> = response
+                .workspaces
+                .iter()
+                .map(|snapshot| snapshot.workspace["id"].clone())
+                .collect();
+            assert_eq!(
+                ids,
+                vec![json!("ws-0"), json!("ws-1"), json!("ws-2"), json!("ws-3")]
+            );
+            assert_eq!(response.workspaces[0].threads[0]["id"], "ws-0-t");
+            assert_eq!(
+                response.workspaces[2].error.as_deref(),
+                Some("workspace offline")
+            );
+            assert!(response.workspaces[3].error.is_none());
+        });
+    }
+
+    #[test]
+    fn drawings_follow_cursor_up_to_depth() {
+        run_async(async {
+            let daemon_addr = spawn_mock_daemon(|method, params| async move {
+                if method == "list_workspaces" {
+                    return Ok(json!([{ "id": "ws-1" }]));
+                }
+                let page = match params["cursor"].as_str() {
+                    None => json!({ "data": [{ "id": "t-1" }], "nextCursor": "c-2" }),
+                    Some("c-2") => json!({ "data": [{ "id": "t-2" }], "nextCursor": "c-3" }),
+                    Some(other) => return Err(format!("unexpected cursor `{other}`")),
+                };
+                Ok(page)
+            })
+            .await;
+            let state = test_state(
+                daemon_addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let Json(response) = list_drawings(
+                State(state),
+                authed_headers(None),
+                Query(DrawingsQuery { depth: Some(2) }),
+            )
+            .await
+            .expect("drawings");
+
+            let snapshot = &response.workspaces[0];
+            assert!(snapshot.error.is_none());
+            assert_eq!(
+                snapshot.threads,
+                vec![json!({ "id": "t-1" }), json!({ "id": "t-2" })]
+            );
+            assert_eq!(snapshot.next_cursor.as_deref(), Some("c-3"));
+        });
+    }
+
+    #[test]
+    fn sse_streams_daemon_events_until_disconnect() {
+        run_async(async {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("bind mock daemon");
+            let daemon_addr = listener.local_addr().expect("mock daemon addr").to_string();
+            tokio::spawn(async move {
+                let Ok((stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                let Ok(Some(line)) = lines.next_line().await else {
+                    return;
+                };
+                let request: Value = serde_json::from_str(&line).expect("ping");
+                let reply = json!({ "id": request["id"], "result": {} });
+                let event = json!({
+                    "method": "app-server-event",
+                    "params": { "workspace_id": "ws-1", "message": {} },
+                });
+                let _ = writer
+                    .write_all(format!("{reply}\n{event}\n").as_bytes())
+                    .await;
+            });
+
+            let addr = spawn_gateway(test_state(
+                daemon_addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            ))
+            .await;
+            let (status, response) = tokio::time::timeout(
+                Duration::from_secs(5),
+                raw_http(addr, "/sse/events?token=secret", None),
+            )
+            .await
+            .expect("sse stream ends");
+
+            assert_eq!(status, 200);
+            assert!(response.contains("text/event-stream"));
+            assert!(response.contains(r#"data: {"method":"app-server-event""#));
+            assert!(response.contains("event: disconnected"));
+        });
+    }
+
+    #[test]
+    fn interrupt_validates_fields_and_requires_turn_id() {
+        run_async(async {
+            let daemon_addr = spawn_mock_daemon(|_method, params| async move { Ok(params) }).await;
+            let state = test_state(
+                daemon_addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            for (workspace_id, thread_id, turn_id, field) in [
+                ("", "t-1", Some("turn-1"), "workspaceId"),
+                ("ws-1", " ", Some("turn-1"), "threadId"),
+                ("ws-1", "t-1", None, "turnId"),
+                ("ws-1", "t-1", Some(" "), "turnId"),
+            ] {
+                let error = interrupt_thread(
+                    State(state.clone()),
+                    authed_headers(None),
+                    Json(InterruptThreadRequest {
+                        workspace_id: workspace_id.to_string(),
+                        thread_id: thread_id.to_string(),
+                        turn_id: turn_id.map(str::to_string),
+                    }),
+                )
+                .await
+                .expect_err("empty field");
+                assert_eq!(error.status, StatusCode::BAD_REQUEST);
+                assert!(error.message.contains(field));
+            }
+
+            let Json(response) = interrupt_thread(
+                State(state),
+                authed_headers(None),
+                Json(InterruptThreadRequest {
+                    workspace_id: "ws-1".to_string(),
+                    thread_id: "t-1".to_string(),
+                    turn_id: Some(" turn-1 ".to_string()),
+                }),
+            )
+            .await
+            .expect("interrupt");
+            assert_eq!(response.result["turnId"], "turn-1");
+        });
+    }
+
+    #[test]
+    fn delete_requires_thread_id() {
+        run_async(async {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let daemon_addr = {
+                let calls = calls.clone();
+                spawn_mock_daemon(move |_method, _params| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    std::future::ready(Ok(json!({})))
+                })
+                .await
+            };
+            let state = test_state(
+                daemon_addr,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            );
+
+            let error = delete_thread(
+                State(state),
+                authed_headers(None),
+                Json(DeleteThreadRequest {
+                    workspace_id: "ws-1".to_string(),
+                    thread_id: "  ".to_string(),
+                }),
+            )
+            .await
+            .expect_err("missing threadId");
+
+            assert_eq!(error.status, StatusCode::BAD_REQUEST);
+            assert!(error.message.contains("threadId"));
+            assert_eq!(calls.load(Ordering::SeqCst), 0);
+        });
+    }
+
+    fn write_temp_config(contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("gateway-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).expect("write config");
+        path
+    }
+
+    #[test]
+    fn config_file_sits_between_cli_flags_and_env_vars() {
+        let path = write_temp_config(
+            r#"
+listen = "0.0.0.0:9000"
+daemon_token = "file-daemon"
+api_token = "file-api"
+daemon_pool_size = 8
+daemon_timeout = 5
+"#,
+        );
+        let args = [
+            "--config",
+            path.to_str().expect("utf-8 path"),
+            "--api-token",
+            "cli-api",
+            "--daemon-pool-size",
+            "2",
+        ]
+        .map(str::to_string);
+        let config = parse_args_from(args, |name| match name {
+            "CODEX_MONITOR_DAEMON_TOKEN" => Some("env-daemon".to_string()),
+            "CODEX_MONITOR_WEB_TOKEN" => Some("env-api".to_string()),
+            _ => None,
+        })
+        .expect("config");
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(config.api_token.as_deref(), Some("cli-api"));
+        assert_eq!(config.daemon_pool_size, 2);
+        assert_eq!(config.daemon_token.as_deref(), Some("file-daemon"));
+        assert_eq!(config.listen, "0.0.0.0:9000".parse().expect("addr"));
+        assert_eq!(config.daemon_timeout, Duration::from_secs(5));
+        assert_eq!(config.daemon_addr, DEFAULT_DAEMON_ADDR);
+
+        let config = parse_args_from(Vec::new(), |name| {
+            (name == "CODEX_MONITOR_WEB_TOKEN").then(|| "env-api".to_string())
+        })
+        .expect("env only");
+        assert_eq!(config.api_token.as_deref(), Some("env-api"));
+    }
+
+    #[test]
+    fn cli_api_token_overrides_file_insecure_no_auth() {
+        let path = write_temp_config("insecure_no_auth = true");
+        let args = [
+            "--config",
+            path.to_str().expect("utf-8 path"),
+            "--api-token",
+            "cli-api",
+        ]
+        .map(str::to_string);
+        let config = parse_args_from(args, |_| None).expect("config");
+        assert_eq!(config.api_token.as_deref(), Some("cli-api"));
+
+        let args = ["--config", path.to_str().expect("utf-8 path")].map(str::to_string);
+        let config = parse_args_from(args, |_| None).expect("config");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(config.api_token, None);
+    }
+
+    #[test]
+    fn blank_file_tokens_fall_back_to_env_vars() {
+        let path = write_temp_config("api_token = \"\"\ndaemon_token = \"  \"");
+        let args = ["--config", path.to_str().expect("utf-8 path")].map(str::to_string);
+        let config = parse_args_from(args, |name| match name {
+            "CODEX_MONITOR_DAEMON_TOKEN" => Some("env-daemon".to_string()),
+            "CODEX_MONITOR_WEB_TOKEN" => Some("env-api".to_string()),
+            _ => None,
+        })
+        .expect("config");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(config.api_token.as_deref(), Some("env-api"));
+        assert_eq!(config.daemon_token.as_deref(), Some("env-daemon"));
+
+        let settings = GatewaySettings::from_toml("api_token = \" web-token \"").expect("toml");
+        assert_eq!(settings.api_token.as_deref(), Some("web-token"));
+    }
+
+    #[test]
+    fn malformed_config_files_are_rejected() {
+        let error = GatewaySettings::from_toml("api_tokn = \"x\"").expect_err("unknown key");
+        assert!(error.contains("api_tokn"), "{error}");
+        assert!(GatewaySettings::from_toml("daemon_pool_size = \"many\"").is_err());
+        assert!(GatewaySettings::from_toml("listen = [").is_err());
+        assert!(GatewaySettings::from_toml("daemon_timeout = 0").is_err());
+
+        let path = write_temp_config("insecure_no_auth = yes");
+        let args = ["--config".to_string(), path.display().to_string()];
+        let Err(error) = parse_args_from(args, |_| None) else {
+            panic!("malformed config file was accepted");
+        };
+        let _ = std::fs::remove_file(&path);
+        assert!(error.contains(&path.display().to_string()), "{error}");
+    }
+
+    #[test]
+    fn websocket_rpcs_time_out_and_are_bounded() {
+        run_async(async {
+            let daemon_addr = spawn_mock_daemon(|method, _params| async move {
+                if method == "hang" {
+                    std::future::pending::<()>().await;
+                }
+                Ok(json!({}))
+            })
+            .await;
+            let mut config = test_config(daemon_addr);
+            config.daemon_timeout = Duration::from_millis(300);
+            let addr = spawn_gateway(state_with_config(
+                config,
+                IdempotencyCache::new(IDEMPOTENCY_TTL, IDEMPOTENCY_MAX_ENTRIES),
+            ))
+            .await;
+            let (mut socket, _) =
+                tokio_tungstenite::connect_async(format!("ws://{addr}/ws/events?token=secret"))
+                    .await
+                    .expect("connect websocket");
+            assert_eq!(next_ws_json(&mut socket).await["type"], "gateway/ready");
+
+            use futures_util::SinkExt;
+            socket
+                .send(tokio_tungstenite::tungstenite::Message::Text(
+                    "not json".into(),
+                ))
+                .await
+                .expect("send garbage");
+            let error = next_ws_json(&mut socket).await;
+            assert_eq!(error["type"], "rpc/error");
+            assert_eq!(error["id"], Value::Null);
+
+            for id in 0..=MAX_WS_PENDING_RPCS {
+                let request = json!({ "id": id, "method": "hang" });
+                socket
+                    .send(tokio_tungstenite::tungstenite::Message::Text(
+                        request.to_string().into(),
+                    ))
+                    .await
+                    .expect("send rpc");
+            }
+            let rejected = next_ws_json(&mut socket).await;
+            assert_eq!(rejected["type"], "rpc/error");
+            assert_eq!(rejected["id"], MAX_WS_PENDING_RPCS);
+            assert!(rejected["error"]
+                .as_str()
+                .is_some_and(|error| error.contains("too many")));
+
+            let timed_out = next_ws_json(&mut socket).await;
+            assert_eq!(timed_out["type"], "rpc/error");
+            assert!(timed_out["error"]
+                .as_str()
+                .is_some_and(|error| error.contains("did not answer `hang`")));
+        });
+    }
 }
@@ -1,6 +1,9 @@
 (() => {
   const TOKEN_STORAGE_KEY = "codexmonitor.webGateway.token";
   const MAX_EVENT_LINES = 300;
+  const WS_PROTOCOL = "codex-monitor.v1";
+  const WS_TOKEN_PROTOCOL_PREFIX = "codex-monitor.token.";
+  const WS_PROTOCOL_SAFE_TOKEN = /^[A-Za-z0-9!#$%&'*+.^_`|~-]+$/;
 
   const state = {
     token: "",
@@ -8,6 +11,9 @@
     activeWorkspaceId: "",
     threads: [],
     activeThreadId: "",
+    runningTurns: {},
+    activity: [],
+    activityCursor: null,
     ws: null,
     refreshThreadsTimer: null,
   };
@@ -25,15 +31,20 @@
     refreshDrawingsBtn: document.getElementById("refresh-drawings-btn"),
     refreshWorkspacesBtn: document.getElementById("refresh-workspaces-btn"),
     drawingsOverview: document.getElementById("drawings-overview"),
+    activityFeed: document.getElementById("activity-feed"),
+    loadMoreActivityBtn: document.getElementById("load-more-activity-btn"),
     workspaceSelect: document.getElementById("workspace-select"),
     refreshThreadsBtn: document.getElementById("refresh-threads-btn"),
     startThreadBtn: document.getElementById("start-thread-btn"),
+    exportWorkspaceBtn: document.getElementById("export-workspace-btn"),
     threadsList: document.getElementById("threads-list"),
     threadIdInput: document.getElementById("thread-id-input"),
     accessModeSelect: document.getElementById("access-mode-select"),
     messageInput: document.getElementById("message-input"),
     sendMessageBtn: document.getElementById("send-message-btn"),
     resumeThreadBtn: document.getElementById("resume-thread-btn"),
+    interruptThreadBtn: document.getElementById("interrupt-thread-btn"),
+    deleteThreadBtn: document.getElementById("delete-thread-btn"),
     rpcMethodInput: document.getElementById("rpc-method-input"),
     rpcParamsInput: document.getElementById("rpc-params-input"),
     runRpcBtn: document.getElementById("run-rpc-btn"),
@@ -134,8 +145,29 @@
     return `Thread ${index + 1}`;
   }
 
-  function renderDrawingsOverview(payload) {
+  const SPARKLINE_BLOCKS = "▁▂▃▄▅▆▇█";
+  const SPARKLINE_HOURS = 24;
+
+  function buildSparkline(stats) {
+    const buckets = Array.isArray(stats?.buckets) ? stats.buckets : [];
+    const bucketSeconds = 3600;
+    const currentHour = Math.floor(Date.now() / 1000 / bucketSeconds) * bucketSeconds;
+    const counts = [];
+    for (let offset = SPARKLINE_HOURS - 1; offset >= 0; offset -= 1) {
+      const hourStart = currentHour - offset * bucketSeconds;
+      const bucket = buckets.find((entry) => Number(entry?.hourStart) === hourStart);
+      counts.push(Number(bucket?.events || 0));
+    }
+    const max = Math.max(...counts);
+    if (max <= 0) return "";
+    return counts
+      .map((count) => SPARKLINE_BLOCKS[Math.round((count / max) * (SPARKLINE_BLOCKS.length - 1))])
+      .join("");
+  }
+
+  function renderDrawingsOverview(payload, statsPayload) {
     if (!els.drawingsOverview) return;
+    const statsList = Array.isArray(statsPayload?.workspaces) ? statsPayload.workspaces : [];
     const list = Array.isArray(payload?.workspaces) ? payload.workspaces : [];
     if (list.length === 0) {
       els.drawingsOverview.innerHTML = "<div class=\"overview-item\">No workspace data.</div>";
@@ -148,13 +180,64 @@
         const name = String(workspace.name || workspace.path || workspace.id || "workspace");
         const count = Array.isArray(entry?.threads) ? entry.threads.length : 0;
         const err = entry?.error ? ` | error: ${String(entry.error)}` : "";
-        return `<div class=\"overview-item\"><strong>${escapeHtml(name)}</strong><br/>threads: ${count}${escapeHtml(err)}</div>`;
+        const stats = statsList.find((item) => item?.workspaceId === workspace.id);
+        const sparkline = buildSparkline(stats);
+        const activity = sparkline
+          ? `<br/><span class=\"sparkline\" title=\"events, last ${SPARKLINE_HOURS}h\">${escapeHtml(sparkline)}</span>`
+          : "";
+        return `<div class=\"overview-item\"><strong>${escapeHtml(name)}</strong><br/>threads: ${count}${escapeHtml(err)}${activity}</div>`;
       })
       .join("");
 
     els.drawingsOverview.innerHTML = html;
   }
 
+  function renderActivity() {
+    if (!els.activityFeed) return;
+    if (state.activity.length === 0) {
+      els.activityFeed.innerHTML = "<div class=\"thread-item\">No recent activity.</div>";
+    } else {
+      const fragment = document.createDocumentFragment();
+      state.activity.forEach((entry, index) => {
+        const thread = entry?.thread || {};
+        const workspaceId = String(entry?.workspaceId || "");
+        const id = extractThreadId(thread);
+        const item = document.createElement("div");
+        item.className = "thread-item";
+
+        const title = document.createElement("div");
+        title.textContent = threadDisplayName(thread, index);
+
+        const meta = document.createElement("div");
+        meta.className = "thread-meta";
+        meta.textContent = `${String(entry?.workspaceName || workspaceId)} | updated: ${formatDate(thread?.updatedAt || thread?.updated_at)}`;
+
+        item.appendChild(title);
+        item.appendChild(meta);
+        item.addEventListener("click", async () => {
+          if (workspaceId && workspaceId !== state.activeWorkspaceId) {
+            state.activeWorkspaceId = workspaceId;
+            if (els.workspaceSelect) {
+              els.workspaceSelect.value = workspaceId;
+            }
+            try {
+              await refreshThreads();
+            } catch (error) {
+              appendEvent("threads/error", String(error));
+            }
+          }
+          selectThread(id);
+        });
+        fragment.appendChild(item);
+      });
+      els.activityFeed.innerHTML = "";
+      els.activityFeed.appendChild(fragment);
+    }
+    if (els.loadMoreActivityBtn) {
+      els.loadMoreActivityBtn.disabled = !state.activityCursor;
+    }
+  }
+
   function renderWorkspaces() {
     if (!els.workspaceSelect) return;
     const previous = state.activeWorkspaceId;
@@ -254,7 +337,33 @@
 
   async function refreshDrawings() {
     const payload = await api("/api/drawings");
-    renderDrawingsOverview(payload);
+    let statsPayload = null;
+    try {
+      statsPayload = await api("/api/stats");
+    } catch (error) {
+      appendEvent("stats/error", String(error));
+    }
+    renderDrawingsOverview(payload, statsPayload);
+    try {
+      await refreshActivity(false);
+    } catch (error) {
+      appendEvent("activity/error", String(error));
+    }
+  }
+
+  async function refreshActivity(append) {
+    const query = new URLSearchParams({ limit: "20" });
+    if (append && state.activityCursor) {
+      query.set("cursor", state.activityCursor);
+    }
+    const payload = await api(`/api/threads/all?${query.toString()}`);
+    const threads = Array.isArray(payload?.threads) ? payload.threads : [];
+    state.activity = append ? state.activity.concat(threads) : threads;
+    state.activityCursor = payload?.nextCursor || null;
+    for (const warning of Array.isArray(payload?.warnings) ? payload.warnings : []) {
+      appendEvent("activity/warning", warning);
+    }
+    renderActivity();
   }
 
   async function refreshWorkspaces() {
@@ -319,6 +428,38 @@
     appendEvent("thread/start", payload);
   }
 
+  async function exportWorkspace() {
+    if (!state.activeWorkspaceId) {
+      throw new Error("Select a workspace first");
+    }
+
+    const query = new URLSearchParams({ workspaceId: state.activeWorkspaceId });
+    const response = await fetch(`/api/workspaces/export?${query.toString()}`, {
+      headers: authHeaders(),
+    });
+    if (!response.ok) {
+      let message = `${response.status} ${response.statusText}`;
+      try {
+        const payload = await response.json();
+        if (payload && payload.error) message = String(payload.error);
+      } catch {
+        // keep the status line
+      }
+      throw new Error(message);
+    }
+
+    const blob = await response.blob();
+    const url = URL.createObjectURL(blob);
+    const link = document.createElement("a");
+    link.href = url;
+    link.download = `codex-workspace-${state.activeWorkspaceId}.zip`;
+    document.body.appendChild(link);
+    link.click();
+    link.remove();
+    URL.revokeObjectURL(url);
+    appendEvent("workspace/export", { workspaceId: state.activeWorkspaceId, bytes: blob.size });
+  }
+
   async function resumeThread() {
     if (!state.activeWorkspaceId) {
       throw new Error("Select a workspace first");
@@ -335,6 +476,47 @@
     appendEvent("thread/resume", payload);
   }
 
+  async function interruptThread() {
+    if (!state.activeWorkspaceId) {
+      throw new Error("Select a workspace first");
+    }
+    const threadId = String((els.threadIdInput && els.threadIdInput.value) || state.activeThreadId || "").trim();
+    if (!threadId) {
+      throw new Error("Select a thread first");
+    }
+
+    const turnId = state.runningTurns[threadId];
+    if (!turnId) {
+      throw new Error("No running turn seen for this thread");
+    }
+
+    const payload = await api("/api/threads/interrupt", {
+      method: "POST",
+      body: JSON.stringify({ workspaceId: state.activeWorkspaceId, threadId, turnId }),
+    });
+    appendEvent("thread/interrupt", payload);
+  }
+
+  async function deleteThread() {
+    if (!state.activeWorkspaceId) {
+      throw new Error("Select a workspace first");
+    }
+    const threadId = String((els.threadIdInput && els.threadIdInput.value) || state.activeThreadId || "").trim();
+    if (!threadId) {
+      throw new Error("Select a thread first");
+    }
+    if (!window.confirm(`Delete thread ${threadId}?`)) {
+      return;
+    }
+
+    const payload = await api("/api/threads/delete", {
+      method: "POST",
+      body: JSON.stringify({ workspaceId: state.activeWorkspaceId, threadId }),
+    });
+    appendEvent("thread/delete", payload);
+    await refreshThreads();
+  }
+
   async function sendMessage() {
     if (!state.activeWorkspaceId) {
       throw new Error("Select a workspace first");
@@ -400,13 +582,19 @@
 
     const proto = window.location.protocol === "https:" ? "wss" : "ws";
     const query = new URLSearchParams();
+    const protocols = [WS_PROTOCOL];
     if (state.token) {
-      query.set("token", state.token);
+      // Subprotocol values must be HTTP tokens; other tokens fall back to the query.
+      if (WS_PROTOCOL_SAFE_TOKEN.test(state.token)) {
+        protocols.push(`${WS_TOKEN_PROTOCOL_PREFIX}${state.token}`);
+      } else {
+        query.set("token", state.token);
+      }
     }
 
     const qs = query.toString();
     const wsUrl = `${proto}://${window.location.host}/ws/events${qs ? `?${qs}` : ""}`;
-    const ws = new WebSocket(wsUrl);
+    const ws = new WebSocket(wsUrl, protocols);
     state.ws = ws;
 
     setBadge(els.wsStatus, "WS: connecting", "warn");
@@ -437,10 +625,17 @@
       }
 
       if (payload?.type === "gateway/ready") {
+        setBadge(els.wsStatus, "WS: connected", "ok");
         appendEvent("gateway/ready", payload);
         return;
       }
 
+      if (payload?.type === "gateway/reconnecting") {
+        setBadge(els.wsStatus, "WS: daemon reconnecting", "warn");
+        appendEvent("gateway/reconnecting", payload);
+        return;
+      }
+
       if (payload?.type === "gateway/error") {
         appendEvent("gateway/error", payload.message || payload);
         return;
@@ -461,6 +656,15 @@
         const threadId = String(
           (message.params && (message.params.threadId || message.params.thread_id)) || ""
         );
+        if (threadId && appMethod === "turn/started") {
+          const turn = message.params.turn || {};
+          const turnId = String(turn.id || message.params.turnId || "");
+          if (turnId) {
+            state.runningTurns[threadId] = turnId;
+          }
+        } else if (threadId && appMethod === "turn/completed") {
+          delete state.runningTurns[threadId];
+        }
         if (threadId && workspaceId === state.activeWorkspaceId) {
           if (state.activeThreadId === "") {
             state.activeThreadId = threadId;
@@ -536,6 +740,14 @@
       }
     });
 
+    els.loadMoreActivityBtn?.addEventListener("click", async () => {
+      try {
+        await refreshActivity(true);
+      } catch (error) {
+        appendEvent("activity/error", String(error));
+      }
+    });
+
     els.refreshWorkspacesBtn?.addEventListener("click", async () => {
       try {
         await refreshWorkspaces();
@@ -571,6 +783,14 @@
       }
     });
 
+    els.exportWorkspaceBtn?.addEventListener("click", async () => {
+      try {
+        await exportWorkspace();
+      } catch (error) {
+        appendEvent("workspace/export/error", String(error));
+      }
+    });
+
     els.resumeThreadBtn?.addEventListener("click", async () => {
       try {
         await resumeThread();
@@ -579,6 +799,22 @@
       }
     });
 
+    els.interruptThreadBtn?.addEventListener("click", async () => {
+      try {
+        await interruptThread();
+      } catch (error) {
+        appendEvent("thread/interrupt/error", String(error));
+      }
+    });
+
+    els.deleteThreadBtn?.addEventListener("click", async () => {
+      try {
+        await deleteThread();
+      } catch (error) {
+        appendEvent("thread/delete/error", String(error));
+      }
+    });
+
     els.sendMessageBtn?.addEventListener("click", async () => {
       try {
         await sendMessage();